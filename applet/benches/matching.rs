@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Measures matching a query against the names of a typical set of installed applications.
+
+use std::hint::black_box;
+
+use cosmic_ext_classic_menu_applet::logic::matching::Matcher;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const APPLICATIONS: &[&str] = &[
+    "Archive Manager",
+    "Avahi SSH Server Browser",
+    "Blender",
+    "Bluetooth Manager",
+    "Calculator",
+    "Calendar",
+    "Camera",
+    "Characters",
+    "Cheese",
+    "Chromium Web Browser",
+    "Clocks",
+    "COSMIC App Library",
+    "COSMIC Edit",
+    "COSMIC Files",
+    "COSMIC Launcher",
+    "COSMIC Media Player",
+    "COSMIC Screenshot",
+    "COSMIC Settings",
+    "COSMIC Store",
+    "COSMIC Terminal",
+    "COSMIC Text Editor",
+    "Contacts",
+    "Disk Usage Analyzer",
+    "Disks",
+    "Document Scanner",
+    "Document Viewer",
+    "Element",
+    "Evolution",
+    "Extension Manager",
+    "Files",
+    "Firefox",
+    "Firefox Developer Edition",
+    "Flatseal",
+    "Fonts",
+    "GIMP",
+    "Geary",
+    "GNOME Boxes",
+    "GNOME Terminal",
+    "Gnome Tweaks",
+    "GParted",
+    "Help",
+    "Htop",
+    "Image Viewer",
+    "Inkscape",
+    "Kalkulačka",
+    "KeePassXC",
+    "Krita",
+    "LibreOffice",
+    "LibreOffice Base",
+    "LibreOffice Calc",
+    "LibreOffice Draw",
+    "LibreOffice Impress",
+    "LibreOffice Math",
+    "LibreOffice Writer",
+    "Logs",
+    "Maps",
+    "Music",
+    "Nautilus",
+    "Neovim",
+    "Network Connections",
+    "Obsidian",
+    "OBS Studio",
+    "Passwords and Keys",
+    "Pavucontrol",
+    "Photos",
+    "Popsicle",
+    "Remmina",
+    "Rhythmbox",
+    "Signal",
+    "Software",
+    "Software & Updates",
+    "Steam",
+    "System Monitor",
+    "Telegram Desktop",
+    "Thunderbird Mail",
+    "Transmission",
+    "Visual Studio Code",
+    "VLC media player",
+    "Weather",
+    "Wireshark",
+    "Zed",
+    "Терминал",
+    "Калькулятор",
+    "Текстовый редактор",
+    "Θερμόμετρο",
+];
+
+const QUERIES: &[&str] = &[
+    "f",
+    "fire",
+    "fierfox",
+    "libreofice",
+    "terminal",
+    "kalkulacka",
+];
+
+fn match_applications(c: &mut Criterion) {
+    for transliterate in [false, true] {
+        let matcher = Matcher::new(transliterate);
+        for query in QUERIES {
+            let name = format!("match {query:?}, transliterate {transliterate}");
+            c.bench_function(&name, |b| {
+                b.iter(|| {
+                    APPLICATIONS
+                        .iter()
+                        .filter_map(|name| matcher.score(black_box(name), black_box(query)))
+                        .max()
+                })
+            });
+        }
+    }
+}
+
+fn match_with_indices(c: &mut Criterion) {
+    let matcher = Matcher::new(true);
+    c.bench_function("match with indices \"libreofice\"", |b| {
+        b.iter(|| {
+            APPLICATIONS
+                .iter()
+                .filter_map(|name| matcher.score_with_indices(black_box(name), "libreofice"))
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, match_applications, match_with_indices);
+criterion_main!(benches);
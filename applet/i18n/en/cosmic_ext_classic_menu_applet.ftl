@@ -17,10 +17,110 @@ utility=Utility
 system-monitor-label=System Monitor
 settings-label=System Settings
 disks-label=Disks
+files-label=Files
+terminal-label=Terminal
 
 # main menu
 search-placeholder=Search apps
 no-apps=No applications found
+run-in-terminal=Run in terminal
+calculator-copy={$expression} · Press Enter to copy
+account-settings=Account settings
+session-uptime=Logged in for {$hours} h {$minutes} min
+session-seat-vt={$seat}, VT {$vt}
+launch-failed=Failed to launch {$name}: {$reason}
+launch-failed-title=Failed to launch {$name}
+launch-failed-repeatedly=This application failed to launch repeatedly
+power-action-failed=Power action failed: {$reason}
+
+# search providers
+search-provider-applications=Applications
+search-provider-calculator=Calculator
+search-provider-commands=Commands
+search-provider-settings-panels=Settings
+search-provider-files=Files
+run-command=Run
+run-command-in-terminal=Run in terminal
+search-provider-gnome-shell=Application search providers
+search-provider-krunner=KRunner plugins
+search-scope-category=Category: {$category}
+recent-searches=Recent searches
+matched-generic-name=Generic name: {$name}
+matched-keyword=Keyword: {$keyword}
+matched-comment=Description mentions: {$word}
+matched-command=Command: {$command}
+
+# settings pages
+settings-page-wireless=Wi-Fi
+settings-page-wireless-keywords=wifi;wireless;network;internet;hotspot;
+settings-page-wired=Wired
+settings-page-wired-keywords=ethernet;network;cable;lan;
+settings-page-vpn=VPN
+settings-page-vpn-keywords=vpn;network;wireguard;openvpn;
+settings-page-bluetooth=Bluetooth
+settings-page-bluetooth-keywords=bluetooth;devices;pairing;headphones;
+settings-page-displays=Displays
+settings-page-displays-keywords=display;monitor;screen;resolution;scale;night light;
+settings-page-sound=Sound
+settings-page-sound-keywords=sound;audio;volume;speakers;microphone;
+settings-page-power=Power & Battery
+settings-page-power-keywords=power;battery;energy;sleep;suspend;
+settings-page-appearance=Appearance
+settings-page-appearance-keywords=appearance;theme;dark;light;accent;colors;
+settings-page-wallpaper=Wallpaper
+settings-page-wallpaper-keywords=wallpaper;background;desktop;
+settings-page-keyboard=Keyboard
+settings-page-keyboard-keywords=keyboard;layout;shortcuts;input;
+settings-page-mouse=Mouse
+settings-page-mouse-keywords=mouse;pointer;cursor;acceleration;
+settings-page-touchpad=Touchpad
+settings-page-touchpad-keywords=touchpad;trackpad;gestures;scrolling;
+settings-page-date-time=Date & Time
+settings-page-date-time-keywords=date;time;clock;timezone;
+settings-page-region-language=Region & Language
+settings-page-region-language-keywords=region;language;locale;formats;
+settings-page-users=Users
+settings-page-users-keywords=users;accounts;password;login;
+settings-page-about=About
+settings-page-about-keywords=about;system;hostname;version;hardware;
+
+# power options
+shutdown=Shut down
+restart=Restart
+log-out=Log out
+lock-screen=Lock screen
+suspend=Suspend
+hibernate=Hibernate
+hybrid-sleep=Hybrid sleep
+suspend-then-hibernate=Suspend, then hibernate
+switch-user=Switch user
+cancel=Cancel
+power-action-inhibited=The following applications are preventing this action:
+power-action-force=Do it anyway
+advanced-restart=Advanced restart
+firmware-setup=UEFI firmware setup
+schedule-power-action=Schedule shutdown or restart
+shutdown-in=Shut down in…
+restart-at=Restart at…
+schedule=Schedule
+schedule-delay={$minutes ->
+    [60] 1 hour
+    [120] 2 hours
+   *[other] {$minutes} minutes
+}
+shutdown-scheduled=Shutting down at {$time}
+restart-scheduled=Restarting at {$time}
+power-action-requires-authentication={$name} (requires authentication)
+power-action-not-permitted={$name} is not permitted by the system policy
+confirm-shutdown=The system will shut down automatically in {$seconds} seconds.
+confirm-restart=The system will restart automatically in {$seconds} seconds.
+confirm-log-out=You will be logged out automatically in {$seconds} seconds.
+confirm-lock-screen=The screen will be locked automatically in {$seconds} seconds.
+confirm-suspend=The system will suspend automatically in {$seconds} seconds.
+confirm-hibernate=The system will hibernate automatically in {$seconds} seconds.
+confirm-hybrid-sleep=The system will enter hybrid sleep automatically in {$seconds} seconds.
+confirm-suspend-then-hibernate=The system will suspend and later hibernate automatically in {$seconds} seconds.
+confirm-switch-user=The login screen will be shown automatically in {$seconds} seconds.
 
 # applet button
 menu-label=Menu
@@ -14,18 +14,34 @@ use cosmic::iced::{
     Alignment,
 };
 use cosmic::{Application, Element};
+use std::collections::HashMap;
+use std::future::Future;
 use std::process;
 use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
 
 use crate::applet_button::AppletButton;
 use crate::applet_menu::AppletMenu;
-use crate::config::{AppletButtonStyle, AppletConfig, RecentApplication};
+use crate::config::{
+    AppletButtonStyle, AppletConfig, AppletState, RecentApplication, RecentCommand,
+};
 use crate::fl;
-use crate::logic::apps::{desktop_files, ApplicationCategory, Event, User};
+use crate::logic::apps::{desktop_files, ApplicationCategory, Event, SessionInfo, User};
+use crate::logic::launcher::{join_exec, parse_exec, LaunchFailure};
+use crate::logic::query::SearchQuery;
+use crate::logic::search::{SearchContext, SearchProviderKind};
+use crate::logic::settings_panels::SettingsPanel;
 use crate::model::application_entry::ApplicationEntry;
+use crate::model::search_result::{SearchAction, SearchResultGroup};
+use crate::power_options::{Capability, Inhibitor, RestartTarget, ScheduledShutdown};
 
 pub const APP_ID: &str = "com.championpeak87.cosmic-ext-classic-menu";
 
+/// How often the power capabilities are queried again while the menu is open.
+const POWER_CAPABILITIES_REFRESH: Duration = Duration::from_secs(10);
+
 /// This is the struct that represents your application.
 /// It is used to define the data that will be used by your application.
 #[derive(Default)]
@@ -36,10 +52,16 @@ pub struct Applet {
     popup: Option<Id>,
     /// The configuration that is used to store the application settings.
     pub config: AppletConfig,
+    /// What the applet learned while being used, e.g. the search history
+    pub state: AppletState,
     /// The search field that is used to filter the applications.
     pub search_field: String,
     /// The list of available applications that are displayed in the menu.
     pub available_applications: Vec<Arc<ApplicationEntry>>,
+    /// Pages of COSMIC Settings offered by the search
+    pub settings_panels: Arc<Vec<SettingsPanel>>,
+    /// Results of the search providers for the current search query
+    pub search_results: Vec<SearchResultGroup>,
     /// The list of available categories that are displayed in the menu.
     pub available_categories: Vec<ApplicationCategory>,
     /// The popup type that is used to determine which popup to display.
@@ -48,6 +70,30 @@ pub struct Applet {
     pub selected_category: Option<ApplicationCategory>,
     /// Currently logged user
     pub current_user: Option<User>,
+    /// Details about the session shown by the user widget
+    pub session_info: Option<SessionInfo>,
+    /// Error shown as a banner at the top of the main menu
+    pub error_message: Option<String>,
+    /// Number of consecutive failed launches, keyed by the launched entry
+    pub launch_failures: HashMap<String, u32>,
+    /// Application whose additional actions are shown in the app list
+    pub expanded_application: Option<String>,
+    /// The configured system tools that are installed, shown in the context menu.
+    pub available_system_tools: Vec<SystemTool>,
+    /// Power action waiting for confirmation
+    pub pending_power_action: Option<PendingPowerAction>,
+    /// Power action blocked by inhibitors, waiting for the user's decision
+    pub inhibited_power_action: Option<InhibitedPowerAction>,
+    /// Targets available for an advanced restart, queried when the menu opens
+    pub restart_targets: Vec<RestartTarget>,
+    /// Whether the advanced restart menu is shown
+    pub show_advanced_restart: bool,
+    /// Availability of the power actions handled by logind
+    pub power_capabilities: HashMap<PowerAction, Capability>,
+    /// Shutdown or restart scheduled with logind
+    pub scheduled_shutdown: Option<ScheduledShutdown>,
+    /// Time typed into the "Restart at…" field, while the scheduling page is shown
+    pub power_schedule_input: Option<String>,
 }
 
 /// This is the enum that contains all the possible variants that your application will need to transmit messages.
@@ -58,71 +104,117 @@ pub enum Message {
     TogglePopup(PopupType),
     PopupClosed(Id),
     SearchFieldInput(String),
+    SearchSubmitted,
+    ClearSearchScope,
+    SearchResultActivated(SearchAction),
     PowerOptionSelected(PowerAction),
+    PowerActionInhibitors(PowerAction, Result<Vec<Inhibitor>, zbus::Error>),
+    PowerActionForced,
+    ShowAdvancedRestart,
+    UpdateRestartTargets(Result<Vec<RestartTarget>, zbus::Error>),
+    AdvancedRestartSelected(RestartTarget),
+    PowerActionConfirmed,
+    PowerActionCancelled,
+    PowerCountdownTick,
+    ShowPowerSchedule,
+    PowerScheduleInput(String),
+    ScheduleShutdownIn(u32),
+    ScheduleRestartAt,
+    CancelScheduledShutdown,
+    UpdateScheduledShutdown(Option<ScheduledShutdown>),
+    ScheduledShutdownTick,
     ApplicationSelected(Arc<ApplicationEntry>),
+    RunInTerminal(Arc<ApplicationEntry>),
+    ToggleApplicationActions(String),
     CategorySelected(ApplicationCategory),
     LaunchTool(SystemTool),
     Zbus(Result<(), zbus::Error>),
     UpdateLoggedUser(Result<User, zbus::Error>),
+    UpdateSessionInfo(Result<SessionInfo, zbus::Error>),
     FileEvent(Event),
+    FileIndexUpdated,
+    SearchProvidersChanged,
     UpdateConfig(AppletConfig),
+    UpdateState(AppletState),
     UpdateAvailableApplications(Vec<Arc<ApplicationEntry>>),
+    UpdateSettingsPanels(Arc<Vec<SettingsPanel>>),
+    UpdateSearchResults(String, Vec<SearchResultGroup>),
     UpdateAvailableCategories(Vec<ApplicationCategory>),
+    UpdateAvailableSystemTools(Vec<SystemTool>),
+    RefreshPowerCapabilities,
+    UpdatePowerCapabilities(Result<HashMap<PowerAction, Capability>, zbus::Error>),
+    LaunchSucceeded(String),
+    LaunchFailed(LaunchFailure),
+    DismissError,
 }
 
 #[derive(Clone, Debug)]
 pub enum SystemTool {
     AppletSettings,
-    SystemSettings,
-    SystemMonitor,
-    DiskManagement,
+    /// The accounts page of COSMIC Settings, opened from the user widget
+    AccountSettings,
+    /// A tool configured for the context menu, with its resolved command line
+    Command {
+        label: String,
+        command: String,
+    },
+    /// A page of COSMIC Settings, found by searching
+    SettingsPage {
+        page: String,
+        name: String,
+    },
 }
 
 impl SystemTool {
-    pub fn perform(&self) {
+    pub async fn perform(self) -> std::io::Result<()> {
         match self {
             SystemTool::AppletSettings => {
-                // Special case requiring async spawning
-                self.handle_applet_settings();
+                // Ships with the applet, so it is never run on the host
+                Self::handle_applet_settings().await
             }
             tool => {
                 // Handle all other tools
-                self.handle_generic_tool(tool);
+                tool.handle_generic_tool(&tool)
             }
         }
     }
 
-    fn handle_applet_settings(&self) {
-        let env_vars: Vec<(String, String)> = std::env::vars().collect();
-        let app_id = Some("com.championpeak87.cosmic-ext-classic-menu.settings");
-
-        // Spawn the asynchronous execution
-        tokio::spawn(async move {
-            let _ = cosmic::desktop::spawn_desktop_exec(
-                "cosmic-ext-classic-menu-settings",
-                env_vars,
-                app_id.as_deref(),
-                false,
-            )
-            .await;
-        });
+    /// Name of the tool as shown in the context menu.
+    pub fn display_name(&self) -> String {
+        match self {
+            SystemTool::AppletSettings => fl!("settings"),
+            SystemTool::AccountSettings => fl!("account-settings"),
+            SystemTool::Command { label, .. } => label.clone(),
+            SystemTool::SettingsPage { name, .. } => name.clone(),
+        }
     }
 
-    /// Determines the executable name for the native environment.
-    fn get_exec_name(&self) -> Option<&'static str> {
+    async fn handle_applet_settings() -> std::io::Result<()> {
+        let args = ["cosmic-ext-classic-menu-settings".to_string()];
+        crate::logic::launcher::spawn_detached(&args, vec![])
+            .await
+            .map(|_| ())
+    }
+
+    /// Determines the command line for the native environment.
+    fn get_command(&self) -> Option<String> {
         match self {
-            SystemTool::SystemSettings => Some("cosmic-settings"),
-            SystemTool::SystemMonitor => Some("gnome-system-monitor"),
-            SystemTool::DiskManagement => Some("gnome-disks"),
+            SystemTool::Command { command, .. } => Some(command.clone()),
+            SystemTool::AccountSettings => Some("cosmic-settings users".to_string()),
+            SystemTool::SettingsPage { page, .. } => Some(format!("cosmic-settings {}", page)),
             // Filter out tools that are handled elsewhere or have no executable
             _ => None,
         }
     }
 
-    fn handle_generic_tool(&self, tool: &SystemTool) {
-        let exec_name = match tool.get_exec_name() {
-            Some(name) => name,
-            None => return, // Stop if the tool is not meant to be executed this way
+    fn handle_generic_tool(&self, tool: &SystemTool) -> std::io::Result<()> {
+        let command = match tool.get_command() {
+            Some(command) => command,
+            None => return Ok(()), // Stop if the tool is not meant to be executed this way
+        };
+        let command_args = crate::logic::launcher::parse_exec(&command);
+        let Some((exec_name, exec_args)) = command_args.split_first() else {
+            return Ok(());
         };
 
         let is_flatpak = std::env::var("FLATPAK_ID").is_ok();
@@ -130,29 +222,113 @@ impl SystemTool {
         // Logic to determine the final command and arguments, centralizing Flatpak handling
         let (main_exec, args) = if is_flatpak {
             // For Flatpak, use `flatpak-spawn` with the `--host` argument
-            ("flatpak-spawn", vec!["--host", exec_name])
+            let mut args = vec!["--host", exec_name.as_str()];
+            args.extend(exec_args.iter().map(String::as_str));
+            ("flatpak-spawn", args)
         } else {
             // For native, use the direct executable name
-            (exec_name, vec![])
+            (
+                exec_name.as_str(),
+                exec_args.iter().map(String::as_str).collect(),
+            )
         };
 
         // Execute the command and provide better error reporting
         if let Err(e) = process::Command::new(main_exec).args(args).spawn() {
             log::error!("Error launching tool '{}': {}", main_exec, e);
+            return Err(e);
         }
+
+        Ok(())
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum PowerAction {
     Shutdown,
     Logout,
     Lock,
     Reboot,
     Suspend,
+    Hibernate,
+    HybridSleep,
+    SuspendThenHibernate,
+    SwitchUser,
+}
+
+/// A power action waiting for confirmation in the popup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PendingPowerAction {
+    pub action: PowerAction,
+    /// Seconds left until the action is performed automatically
+    pub remaining: u32,
+}
+
+/// A power action blocked by inhibitor locks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InhibitedPowerAction {
+    pub action: PowerAction,
+    pub inhibitors: Vec<Inhibitor>,
 }
 
 impl PowerAction {
+    /// Seconds the built-in confirmation waits before performing the action.
+    pub const CONFIRMATION_COUNTDOWN: u32 = 60;
+
+    /// All power actions, in the order in which they appear in the power menu.
+    pub const ALL: [PowerAction; 9] = [
+        PowerAction::SwitchUser,
+        PowerAction::Logout,
+        PowerAction::Suspend,
+        PowerAction::SuspendThenHibernate,
+        PowerAction::HybridSleep,
+        PowerAction::Hibernate,
+        PowerAction::Lock,
+        PowerAction::Reboot,
+        PowerAction::Shutdown,
+    ];
+
+    /// Whether the action is only offered once it is reported as supported.
+    pub fn requires_support_check(&self) -> bool {
+        matches!(
+            self,
+            PowerAction::Hibernate
+                | PowerAction::HybridSleep
+                | PowerAction::SuspendThenHibernate
+                | PowerAction::SwitchUser
+        )
+    }
+
+    pub fn display_name(&self) -> String {
+        match self {
+            PowerAction::Shutdown => fl!("shutdown"),
+            PowerAction::Logout => fl!("log-out"),
+            PowerAction::Lock => fl!("lock-screen"),
+            PowerAction::Reboot => fl!("restart"),
+            PowerAction::Suspend => fl!("suspend"),
+            PowerAction::Hibernate => fl!("hibernate"),
+            PowerAction::HybridSleep => fl!("hybrid-sleep"),
+            PowerAction::SuspendThenHibernate => fl!("suspend-then-hibernate"),
+            PowerAction::SwitchUser => fl!("switch-user"),
+        }
+    }
+
+    pub fn confirmation_message(&self, seconds: u32) -> String {
+        match self {
+            PowerAction::Shutdown => fl!("confirm-shutdown", seconds = seconds),
+            PowerAction::Logout => fl!("confirm-log-out", seconds = seconds),
+            PowerAction::Lock => fl!("confirm-lock-screen", seconds = seconds),
+            PowerAction::Reboot => fl!("confirm-restart", seconds = seconds),
+            PowerAction::Suspend => fl!("confirm-suspend", seconds = seconds),
+            PowerAction::Hibernate => fl!("confirm-hibernate", seconds = seconds),
+            PowerAction::HybridSleep => fl!("confirm-hybrid-sleep", seconds = seconds),
+            PowerAction::SuspendThenHibernate => {
+                fl!("confirm-suspend-then-hibernate", seconds = seconds)
+            }
+            PowerAction::SwitchUser => fl!("confirm-switch-user", seconds = seconds),
+        }
+    }
+
     fn perform(self) -> cosmic::iced::Task<cosmic::Action<Message>> {
         let msg = |m| cosmic::Action::App(Message::Zbus(m));
         match self {
@@ -169,6 +345,18 @@ impl PowerAction {
             PowerAction::Suspend => {
                 cosmic::iced::Task::perform(crate::power_options::suspend(), msg)
             }
+            PowerAction::Hibernate => {
+                cosmic::iced::Task::perform(crate::power_options::hibernate(), msg)
+            }
+            PowerAction::HybridSleep => {
+                cosmic::iced::Task::perform(crate::power_options::hybrid_sleep(), msg)
+            }
+            PowerAction::SuspendThenHibernate => {
+                cosmic::iced::Task::perform(crate::power_options::suspend_then_hibernate(), msg)
+            }
+            PowerAction::SwitchUser => {
+                cosmic::iced::Task::perform(crate::power_options::switch_user(), msg)
+            }
         }
     }
 }
@@ -220,23 +408,42 @@ impl Application for Applet {
             popup: None,
             search_field: "".to_owned(),
             available_applications: vec![],
+            settings_panels: Arc::default(),
+            search_results: vec![],
             available_categories: vec![],
             popup_type: PopupType::MainMenu,
             selected_category: Some(ApplicationCategory::ALL),
             config: AppletConfig::config(),
+            state: AppletState::state(),
             current_user: None,
+            session_info: None,
+            error_message: None,
+            launch_failures: HashMap::new(),
+            expanded_application: None,
+            available_system_tools: vec![],
+            pending_power_action: None,
+            inhibited_power_action: None,
+            restart_targets: vec![],
+            show_advanced_restart: false,
+            power_capabilities: HashMap::new(),
+            scheduled_shutdown: None,
+            power_schedule_input: None,
         };
 
-        // fetch current user asynchronously
-        let fetch_current_user_task =
-            Task::perform(crate::logic::apps::get_current_user(), |result| {
-                cosmic::Action::App(Message::UpdateLoggedUser(result))
+        let fetch_session_info_task =
+            Task::perform(crate::logic::apps::get_session_info(), |result| {
+                cosmic::Action::App(Message::UpdateSessionInfo(result))
             });
 
         let fetch_all_apps_task = Task::perform(crate::logic::apps::Apps::load_apps(), |res| {
             cosmic::Action::App(Message::UpdateAvailableApplications(res))
         });
 
+        let fetch_settings_panels_task =
+            Task::perform(crate::logic::settings_panels::load_panels(), |res| {
+                cosmic::Action::App(Message::UpdateSettingsPanels(res))
+            });
+
         let fetch_available_categories_task =
             Task::perform(crate::logic::apps::Apps::load_app_categories(), |res| {
                 cosmic::Action::App(Message::UpdateAvailableCategories(res))
@@ -245,8 +452,9 @@ impl Application for Applet {
         (
             window,
             Task::batch(vec![
-                fetch_current_user_task,
+                fetch_session_info_task,
                 fetch_all_apps_task,
+                fetch_settings_panels_task,
                 fetch_available_categories_task,
             ]),
         )
@@ -267,7 +475,7 @@ impl Application for Applet {
         let panel_type = &self.core.applet.panel_type;
         let size = &self.core.applet.size;
 
-        match applet_button_style {
+        let button = match applet_button_style {
             AppletButtonStyle::IconOnly => AppletButton::view_icon_only(&self),
             AppletButtonStyle::LabelOnly => AppletButton::view_label_only(&self),
             AppletButtonStyle::IconAndLabel => AppletButton::view_icon_and_label(&self),
@@ -300,7 +508,9 @@ impl Application for Applet {
                     AppletButton::view_icon_only(&self)
                 }
             },
-        }
+        };
+
+        AppletButton::with_scheduled_shutdown_badge(&self, button)
     }
 
     fn view_window(&self, _id: Id) -> Element<'_, Message> {
@@ -318,8 +528,133 @@ impl Application for Applet {
             Message::TogglePopup(popup_type) => self.toggle_popup(popup_type),
             Message::PopupClosed(id) => self.close_popup(id),
             Message::SearchFieldInput(input) => self.update_search_field(&input),
-            Message::PowerOptionSelected(action) => self.perform_power_action(action),
-            Message::ApplicationSelected(app) => self.launch_application(app),
+            Message::SearchSubmitted => {
+                // Enter activates the top result
+                let top_result = self
+                    .search_results
+                    .first()
+                    .and_then(|group| group.results.first())
+                    .map(|result| result.action.clone());
+                match top_result {
+                    Some(action) => self.activate_search_result(action),
+                    None => Task::none(),
+                }
+            }
+            Message::ClearSearchScope => {
+                let query = SearchQuery::parse(&self.search_field);
+                self.update_search_field(&query.text)
+            }
+            Message::SearchResultActivated(action) => self.activate_search_result(action),
+            Message::PowerOptionSelected(action) => {
+                Task::perform(crate::power_options::inhibitors(action), move |res| {
+                    cosmic::action::app(Message::PowerActionInhibitors(action, res))
+                })
+            }
+            Message::PowerActionInhibitors(action, result) => match result {
+                Ok(inhibitors) if !inhibitors.is_empty() => {
+                    self.inhibited_power_action = Some(InhibitedPowerAction { action, inhibitors });
+
+                    Task::none()
+                }
+                Ok(_) => self.perform_power_action(action),
+                Err(e) => {
+                    log::warn!("Failed to list inhibitors: {}", e);
+                    self.perform_power_action(action)
+                }
+            },
+            // Going ahead despite the inhibitors still asks for the usual confirmation
+            Message::PowerActionForced => match self.inhibited_power_action.take() {
+                Some(inhibited) => self.perform_power_action(inhibited.action),
+                None => Task::none(),
+            },
+            Message::PowerActionConfirmed => self.confirm_power_action(),
+            Message::ShowAdvancedRestart => {
+                self.show_advanced_restart = true;
+
+                Task::none()
+            }
+            Message::UpdateRestartTargets(result) => {
+                match result {
+                    Ok(targets) => self.restart_targets = targets,
+                    Err(e) => {
+                        log::error!("Failed to list restart targets: {}", e);
+                        self.restart_targets = vec![];
+                    }
+                }
+
+                Task::none()
+            }
+            Message::AdvancedRestartSelected(target) => {
+                self.show_advanced_restart = false;
+                let mut tasks = vec![Task::perform(
+                    crate::power_options::restart_into(target),
+                    |res| cosmic::action::app(Message::Zbus(res)),
+                )];
+                if let Some(p) = self.popup.take() {
+                    tasks.push(destroy_popup(p));
+                }
+                Task::batch(tasks)
+            }
+            Message::PowerActionCancelled => {
+                self.pending_power_action = None;
+                self.inhibited_power_action = None;
+                self.show_advanced_restart = false;
+                self.power_schedule_input = None;
+
+                Task::none()
+            }
+            Message::PowerCountdownTick => self.tick_power_countdown(),
+            Message::ShowPowerSchedule => {
+                self.power_schedule_input = Some(String::new());
+
+                Task::none()
+            }
+            Message::PowerScheduleInput(input) => {
+                self.power_schedule_input = Some(input);
+
+                Task::none()
+            }
+            Message::ScheduleShutdownIn(minutes) => {
+                let at = chrono::Local::now() + chrono::Duration::minutes(minutes.into());
+                self.schedule_power_action(PowerAction::Shutdown, at)
+            }
+            Message::ScheduleRestartAt => {
+                let at = self
+                    .power_schedule_input
+                    .as_deref()
+                    .and_then(|input| chrono::NaiveTime::parse_from_str(input.trim(), "%H:%M").ok())
+                    .and_then(crate::power_options::next_occurrence);
+                match at {
+                    Some(at) => self.schedule_power_action(PowerAction::Reboot, at),
+                    None => Task::none(),
+                }
+            }
+            Message::CancelScheduledShutdown => {
+                Task::perform(crate::power_options::cancel_scheduled_shutdown(), |res| {
+                    cosmic::action::app(match res {
+                        Ok(()) => Message::UpdateScheduledShutdown(None),
+                        Err(e) => Message::Zbus(Err(e)),
+                    })
+                })
+            }
+            Message::UpdateScheduledShutdown(scheduled) => {
+                self.scheduled_shutdown = scheduled;
+
+                Task::none()
+            }
+            // Only redraws the countdown on the panel button
+            Message::ScheduledShutdownTick => Task::none(),
+            Message::ApplicationSelected(app) => self.launch_application(app, false),
+            Message::RunInTerminal(app) => self.launch_application(app, true),
+            Message::ToggleApplicationActions(app_id) => {
+                if self.expanded_application.as_ref() == Some(&app_id) {
+                    self.expanded_application = None;
+                } else {
+                    self.expanded_application = Some(app_id);
+                }
+
+                Task::none()
+            }
             Message::CategorySelected(category) => self.select_category(category),
             Message::LaunchTool(tool) => self.launch_tool(tool),
             Message::Zbus(result) => self.handle_zbus_result(result),
@@ -327,20 +662,83 @@ impl Application for Applet {
                 self.current_user = user.ok();
                 Task::none()
             }
+            Message::UpdateSessionInfo(session_info) => {
+                match session_info {
+                    Ok(session_info) => self.session_info = Some(session_info),
+                    Err(e) => log::warn!("Failed to query session information: {}", e),
+                }
+
+                Task::none()
+            }
             Message::FileEvent(event) => self.handle_event(event),
+            Message::FileIndexUpdated | Message::SearchProvidersChanged => {
+                // Show results that were added while searching
+                if self.search_field.is_empty() {
+                    Task::none()
+                } else {
+                    self.update_search_field(&self.search_field.clone())
+                }
+            }
             Message::UpdateConfig(config) => {
                 self.config = config;
 
                 Task::none()
             }
+            Message::UpdateState(state) => {
+                self.state = state;
+
+                Task::none()
+            }
             Message::UpdateAvailableApplications(items) => {
                 self.available_applications = items;
 
                 Task::none()
             }
+            Message::UpdateSettingsPanels(panels) => {
+                self.settings_panels = panels;
+
+                Task::none()
+            }
+            Message::UpdateSearchResults(query, results) => {
+                // Ignore results of queries the user has already typed past
+                if query == self.search_field {
+                    self.search_results = results;
+                }
+
+                Task::none()
+            }
             Message::UpdateAvailableCategories(items) => {
                 self.available_categories = items;
 
+                Task::none()
+            }
+            Message::UpdateAvailableSystemTools(items) => {
+                self.available_system_tools = items;
+
+                Task::none()
+            }
+            Message::RefreshPowerCapabilities => {
+                Task::perform(crate::power_options::capabilities(), |res| {
+                    cosmic::action::app(Message::UpdatePowerCapabilities(res))
+                })
+            }
+            Message::UpdatePowerCapabilities(result) => {
+                match result {
+                    Ok(capabilities) => self.power_capabilities = capabilities,
+                    Err(e) => log::error!("Failed to query power capabilities: {}", e),
+                }
+
+                Task::none()
+            }
+            Message::LaunchSucceeded(id) => {
+                self.launch_failures.remove(&id);
+
+                Task::none()
+            }
+            Message::LaunchFailed(failure) => self.handle_launch_failure(failure),
+            Message::DismissError => {
+                self.error_message = None;
+
                 Task::none()
             }
         }
@@ -356,24 +754,127 @@ impl Application for Applet {
     /// emit messages to the application through a channel. They are started at the
     /// beginning of the application, and persist through its lifetime.
     fn subscription(&self) -> Subscription<Self::Message> {
-        Subscription::batch(vec![
+        let mut subscriptions = vec![
             desktop_files(self.core.main_window_id()).map(Message::FileEvent),
             // Watch for application configuration changes.
             self.core
                 .watch_config::<AppletConfig>(Self::APP_ID)
                 .map(|update| Message::UpdateConfig(update.config)),
-        ])
+            // Watch for the search history being cleared in the settings.
+            self.core
+                .watch_state::<AppletState>(Self::APP_ID)
+                .map(|update| Message::UpdateState(update.config)),
+        ];
+
+        // The current user is fetched on startup and refreshed when the profile changes
+        subscriptions
+            .push(crate::logic::apps::current_user_subscription().map(Message::UpdateLoggedUser));
+
+        subscriptions.push(
+            crate::power_options::scheduled_shutdown_subscription()
+                .map(Message::UpdateScheduledShutdown),
+        );
+
+        subscriptions.push(
+            crate::power_options::capabilities_watcher().map(|_| Message::RefreshPowerCapabilities),
+        );
+
+        // Not every change of the capabilities is announced, so keep the open menu current
+        if self.popup.is_some() && self.popup_type == PopupType::MainMenu {
+            subscriptions.push(
+                cosmic::iced::time::every(POWER_CAPABILITIES_REFRESH)
+                    .map(|_| Message::RefreshPowerCapabilities),
+            );
+        }
+
+        // A query scoped with `f:` indexes the files even while the provider is disabled
+        let roots = crate::logic::file_search::search_roots(&self.config.file_search_roots);
+        if self
+            .config
+            .search_providers
+            .contains(&SearchProviderKind::Files)
+            || crate::logic::file_search::has_index(&roots)
+        {
+            subscriptions.push(
+                crate::logic::file_search::index_watcher(self.core.main_window_id(), roots)
+                    .map(|_| Message::FileIndexUpdated),
+            );
+        }
+
+        if self
+            .config
+            .search_providers
+            .contains(&SearchProviderKind::GnomeShell)
+        {
+            subscriptions.push(
+                crate::logic::gnome_search::providers_watcher(self.core.main_window_id())
+                    .map(|_| Message::SearchProvidersChanged),
+            );
+        }
+
+        if self
+            .config
+            .search_providers
+            .contains(&SearchProviderKind::KRunner)
+        {
+            subscriptions.push(
+                crate::logic::krunner::runners_watcher(self.core.main_window_id())
+                    .map(|_| Message::SearchProvidersChanged),
+            );
+        }
+
+        if self.scheduled_shutdown.is_some() && self.config.show_scheduled_shutdown_countdown {
+            subscriptions.push(
+                cosmic::iced::time::every(Duration::from_secs(1))
+                    .map(|_| Message::ScheduledShutdownTick),
+            );
+        }
+
+        // Drive the countdown of a pending power action
+        if self.pending_power_action.is_some() {
+            subscriptions.push(
+                cosmic::iced::time::every(Duration::from_secs(1))
+                    .map(|_| Message::PowerCountdownTick),
+            );
+        }
+
+        Subscription::batch(subscriptions)
     }
 }
 
 impl Applet {
+    /// Whether a power action should be offered in the power menu.
+    ///
+    /// Actions the system doesn't support are hidden, while actions that need
+    /// authentication or are forbidden by policy stay visible, so the power menu can
+    /// explain what happens when they are used.
+    pub fn is_power_action_available(&self, action: &PowerAction) -> bool {
+        if !self.config.enabled_power_actions.contains(action) {
+            return false;
+        }
+
+        match self.power_capabilities.get(action) {
+            Some(Capability::Yes) | Some(Capability::Challenge) => true,
+            Some(Capability::NotAvailable) => false,
+            Some(Capability::No) | None => !action.requires_support_check(),
+        }
+    }
+
     pub fn handle_event(&mut self, event: Event) -> Task<Message> {
         match event {
             Event::Changed => {
+                // Search providers of removed applications would no longer answer
+                crate::logic::gnome_search::invalidate_providers();
+
                 // Update set of available applications
-                Task::perform(crate::logic::apps::Apps::load_apps(), |res| {
-                    cosmic::Action::App(Message::UpdateAvailableApplications(res))
-                })
+                Task::batch([
+                    Task::perform(crate::logic::apps::Apps::load_apps(), |res| {
+                        cosmic::Action::App(Message::UpdateAvailableApplications(res))
+                    }),
+                    Task::perform(crate::logic::settings_panels::load_panels(), |res| {
+                        cosmic::Action::App(Message::UpdateSettingsPanels(res))
+                    }),
+                ])
             }
         }
     }
@@ -386,6 +887,32 @@ impl Applet {
                 crate::logic::apps::Apps::load_apps(),
                 |res| cosmic::action::app(Message::UpdateAvailableApplications(res)),
             ));
+            tasks.push(Task::perform(crate::power_options::capabilities(), |res| {
+                cosmic::action::app(Message::UpdatePowerCapabilities(res))
+            }));
+            tasks.push(Task::perform(
+                crate::power_options::restart_targets(),
+                |res| cosmic::action::app(Message::UpdateRestartTargets(res)),
+            ));
+        } else {
+            // Tools that aren't installed are hidden from the context menu
+            let system_tools = self.config.system_tools.clone();
+            tasks.push(Task::perform(
+                async move {
+                    tokio::task::spawn_blocking(move || {
+                        crate::logic::system_tools::available_tools(&system_tools)
+                    })
+                    .await
+                    .unwrap_or_default()
+                },
+                |res| {
+                    cosmic::action::app(Message::UpdateAvailableSystemTools(
+                        res.into_iter()
+                            .map(|(label, command)| SystemTool::Command { label, command })
+                            .collect(),
+                    ))
+                },
+            ));
         }
 
         if let Some(p) = self.popup.take() {
@@ -417,6 +944,13 @@ impl Applet {
 
     fn close_popup(&mut self, id: Id) -> Task<Message> {
         self.search_field.clear();
+        self.search_results.clear();
+        self.expanded_application = None;
+        // Closing the popup cancels any pending power action
+        self.pending_power_action = None;
+        self.inhibited_power_action = None;
+        self.show_advanced_restart = false;
+        self.power_schedule_input = None;
         self.selected_category = Some(ApplicationCategory::ALL);
         self.available_applications = Vec::new();
 
@@ -433,19 +967,148 @@ impl Applet {
         if input.is_empty() {
             self.selected_category = Some(ApplicationCategory::ALL);
             self.search_field = input.to_string();
+            self.search_results.clear();
             Task::perform(crate::logic::apps::Apps::load_apps(), |res| {
                 cosmic::action::app(Message::UpdateAvailableApplications(res))
             })
         } else {
             self.search_field = input.to_string();
+            let query = self.search_field.clone();
             Task::perform(
-                crate::logic::apps::Apps::load_filtered_apps(self.search_field.clone()),
-                |res| cosmic::action::app(Message::UpdateAvailableApplications(res)),
+                crate::logic::search::search(
+                    query.clone(),
+                    self.config.search_providers.clone(),
+                    self.search_context(),
+                ),
+                move |res| cosmic::action::app(Message::UpdateSearchResults(query, res)),
             )
         }
     }
 
+    /// Snapshots what the search providers need from the config and state.
+    fn search_context(&self) -> SearchContext {
+        SearchContext {
+            command_history: self.state.command_history.clone(),
+            settings_panels: self.settings_panels.clone(),
+            file_search_roots: crate::logic::file_search::search_roots(
+                &self.config.file_search_roots,
+            ),
+            transliterate: self.config.transliterate_search,
+            search_history: self.state.search_history.clone(),
+        }
+    }
+
+    fn activate_search_result(&mut self, action: SearchAction) -> Task<Message> {
+        // Launched applications are recorded by `launch_application`
+        if !matches!(action, SearchAction::LaunchApplication(_)) {
+            self.record_search(None);
+        }
+
+        match action {
+            SearchAction::LaunchApplication(app) => self.launch_application(app, false),
+            SearchAction::CopyToClipboard(value) => {
+                let mut tasks = vec![cosmic::iced::clipboard::write(value)];
+                if let Some(p) = self.popup.take() {
+                    tasks.push(destroy_popup(p));
+                }
+                Task::batch(tasks)
+            }
+            SearchAction::ActivateGnomeShellResult(result) => {
+                let failure = LaunchFailure {
+                    id: format!("{}:{}", result.bus_name, result.identifier),
+                    name: result.provider_name.clone(),
+                    reason: String::new(),
+                };
+                self.activate_remote_result(
+                    crate::logic::gnome_search::activate_result(result),
+                    failure,
+                )
+            }
+            SearchAction::RunKRunnerMatch(result) => {
+                let failure = LaunchFailure {
+                    id: format!("{}:{}", result.bus_name, result.match_id),
+                    name: result.runner_name.clone(),
+                    reason: String::new(),
+                };
+                self.activate_remote_result(crate::logic::krunner::run_result(result), failure)
+            }
+            SearchAction::RunCommand(command) => self.run_command(command),
+            SearchAction::LaunchTool(tool) => self.launch_tool(tool),
+            SearchAction::OpenPath(path) => {
+                let mut tasks = vec![];
+                if let Err(e) = open::that_detached(&path) {
+                    tasks.push(
+                        self.handle_launch_failure(LaunchFailure {
+                            id: path.display().to_string(),
+                            name: path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default(),
+                            reason: e.to_string(),
+                        }),
+                    );
+                }
+                if let Some(p) = self.popup.take() {
+                    tasks.push(destroy_popup(p));
+                }
+                Task::batch(tasks)
+            }
+        }
+    }
+
+    /// Closes the popup while a result is activated over D-Bus, reporting a failed call.
+    fn activate_remote_result(
+        &mut self,
+        activation: impl Future<Output = zbus::Result<()>> + Send + 'static,
+        failure: LaunchFailure,
+    ) -> Task<Message> {
+        let mut tasks = vec![Task::perform(activation, move |res| match res {
+            Ok(()) => cosmic::action::none(),
+            Err(e) => cosmic::action::app(Message::LaunchFailed(LaunchFailure {
+                reason: e.to_string(),
+                ..failure
+            })),
+        })];
+        if let Some(p) = self.popup.take() {
+            tasks.push(destroy_popup(p));
+        }
+        Task::batch(tasks)
+    }
+
     fn perform_power_action(&mut self, action: PowerAction) -> Task<Message> {
+        if !self.config.confirm_power_actions.contains(&action) {
+            return action.perform();
+        }
+
+        if !self.config.builtin_power_confirmation {
+            if let Some(task) = self.confirm_with_osd(action) {
+                return task;
+            }
+        }
+
+        self.pending_power_action = Some(PendingPowerAction {
+            action,
+            remaining: PowerAction::CONFIRMATION_COUNTDOWN,
+        });
+
+        Task::none()
+    }
+
+    /// Schedules a shutdown or restart and returns to the main menu, which then shows it.
+    fn schedule_power_action(
+        &mut self,
+        action: PowerAction,
+        at: chrono::DateTime<chrono::Local>,
+    ) -> Task<Message> {
+        self.power_schedule_input = None;
+
+        Task::perform(crate::power_options::schedule_shutdown(action, at), |res| {
+            cosmic::action::app(Message::Zbus(res))
+        })
+    }
+
+    /// Hands the confirmation over to `cosmic-osd`, returns `None` if it can't be used.
+    fn confirm_with_osd(&mut self, action: PowerAction) -> Option<Task<Message>> {
         let is_flatpak = std::env::var("FLATPAK_ID").is_ok();
         let main_exec = if is_flatpak {
             "flatpak-spawn"
@@ -467,43 +1130,164 @@ impl Applet {
             PowerAction::Shutdown => {
                 args.push("shutdown");
             }
-            _ => return action.perform(),
+            _ => return None,
         };
 
-        if let Err(_) = process::Command::new(main_exec).args(args).spawn() {
-            return action.perform();
+        if process::Command::new(main_exec).args(args).spawn().is_err() {
+            return None;
         }
 
         if let Some(p) = self.popup.take() {
-            return destroy_popup(p);
+            return Some(destroy_popup(p));
         }
 
-        Task::none()
+        Some(Task::none())
     }
 
-    fn launch_application(&mut self, app: Arc<ApplicationEntry>) -> Task<Message> {
-        let mut app_exec = app.exec.clone().unwrap();
+    fn confirm_power_action(&mut self) -> Task<Message> {
+        let Some(pending) = self.pending_power_action.take() else {
+            return Task::none();
+        };
+
+        let mut tasks = vec![pending.action.perform()];
+        if let Some(p) = self.popup.take() {
+            tasks.push(destroy_popup(p));
+        }
+        Task::batch(tasks)
+    }
+
+    fn tick_power_countdown(&mut self) -> Task<Message> {
+        match self.pending_power_action.as_mut() {
+            Some(pending) if pending.remaining > 1 => {
+                pending.remaining -= 1;
+                Task::none()
+            }
+            Some(_) => self.confirm_power_action(),
+            None => Task::none(),
+        }
+    }
+
+    fn launch_application(
+        &mut self,
+        app: Arc<ApplicationEntry>,
+        run_in_terminal: bool,
+    ) -> Task<Message> {
+        let app_exec = app.exec.clone().unwrap();
         let env_vars: Vec<(String, String)> = std::env::vars().collect();
         let app_id = Some(app.id.clone());
-        let is_terminal = app.is_terminal;
+        let terminal = (app.is_terminal || run_in_terminal).then(|| self.config.terminal.clone());
 
         let is_flatpak = std::env::var("FLATPAK_ID").is_ok();
+        // Processes spawned inside the sandbox can't be moved into a host scope
+        let in_scope = self.config.launch_in_scope && !is_flatpak;
+        self.expanded_application = None;
+
+        let launch_task = Task::perform(
+            crate::logic::launcher::launch(app_exec, env_vars, app_id, terminal, in_scope),
+            {
+                let app = app.clone();
+                move |result| match result {
+                    Ok(()) => cosmic::action::app(Message::LaunchSucceeded(app.id.clone())),
+                    Err(e) => cosmic::action::app(Message::LaunchFailed(LaunchFailure {
+                        id: app.id.clone(),
+                        name: app.name.clone(),
+                        reason: e.to_string(),
+                    })),
+                }
+            },
+        );
 
-        if is_flatpak {
-            app_exec = format!("flatpak-spawn --host {}", app_exec);
+        if !self.search_field.is_empty() {
+            self.record_search(Some(app.id.clone()));
         }
+        self.update_recent_applications(app);
 
-        tokio::spawn(async move {
-            cosmic::desktop::spawn_desktop_exec(app_exec, env_vars, app_id.as_deref(), is_terminal)
-                .await;
-        });
+        if let Some(p) = self.popup.take() {
+            return Task::batch(vec![launch_task, destroy_popup(p)]);
+        }
+        launch_task
+    }
 
-        self.update_recent_applications(app);
+    /// Runs a command typed into the search field through `sh`, so that pipes and
+    /// variables work as in a terminal.
+    fn run_command(&mut self, command: RecentCommand) -> Task<Message> {
+        let program = parse_exec(&command.command)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        let args = ["sh", "-c", command.command.as_str()].map(str::to_string);
+        // Percent signs would be taken for field codes
+        let exec = join_exec(&args).replace('%', "%%");
+        let env_vars: Vec<(String, String)> = std::env::vars().collect();
+        let terminal = command.in_terminal.then(|| self.config.terminal.clone());
+        let in_scope = self.config.launch_in_scope && std::env::var("FLATPAK_ID").is_err();
+
+        let failure = LaunchFailure {
+            id: format!("command:{}", command.command),
+            name: command.command.clone(),
+            reason: String::new(),
+        };
+        let launch_task = Task::perform(
+            crate::logic::launcher::launch(exec, env_vars, Some(program), terminal, in_scope),
+            move |result| match result {
+                Ok(()) => cosmic::action::none(),
+                Err(e) => cosmic::action::app(Message::LaunchFailed(LaunchFailure {
+                    reason: e.to_string(),
+                    ..failure
+                })),
+            },
+        );
+
+        crate::logic::commands::add_to_history(&mut self.state.command_history, command);
+        // Failing to remember the command mustn't get in the way of running it
+        self.write_state();
 
         if let Some(p) = self.popup.take() {
-            return destroy_popup(p);
+            return Task::batch(vec![launch_task, destroy_popup(p)]);
+        }
+        launch_task
+    }
+
+    fn handle_launch_failure(&mut self, failure: LaunchFailure) -> Task<Message> {
+        log::error!("Error launching '{}': {}", failure.name, failure.reason);
+
+        let failure_count = self.launch_failures.entry(failure.id.clone()).or_insert(0);
+        *failure_count = failure_count.saturating_add(1);
+
+        self.error_message = Some(fl!(
+            "launch-failed",
+            name = failure.name.clone(),
+            reason = failure.reason.clone()
+        ));
+
+        // The popup is usually closed by the time a launch fails, so notify as well
+        Task::perform(crate::logic::launcher::notify_failure(failure), |_| {
+            cosmic::action::none()
+        })
+    }
+
+    /// Adds the current query to the search history.
+    fn record_search(&mut self, app_id: Option<String>) {
+        crate::logic::search_history::record(
+            &mut self.state.search_history,
+            &self.search_field,
+            app_id,
+        );
+
+        // Failing to remember the query mustn't get in the way of the activated result
+        self.write_state();
+    }
+
+    /// Writes the search and command history, logging failures.
+    fn write_state(&self) {
+        match AppletState::state_handler() {
+            Some(handler) => {
+                if let Err(e) = self.state.write_entry(&handler) {
+                    log::error!("Failed to write the applet state: {}", e);
+                }
+            }
+            None => log::error!("Failed to open the applet state"),
         }
-        Task::none()
     }
 
     fn update_recent_applications(&mut self, app: Arc<ApplicationEntry>) {
@@ -530,6 +1314,7 @@ impl Applet {
 
     fn select_category(&mut self, category: ApplicationCategory) -> Task<Message> {
         self.search_field.clear();
+        self.search_results.clear();
         self.selected_category = Some(category.clone());
 
         Task::perform(
@@ -539,53 +1324,80 @@ impl Applet {
     }
 
     fn launch_tool(&mut self, tool: SystemTool) -> Task<Message> {
-        tool.perform();
+        let failure = LaunchFailure {
+            id: format!("{:?}", tool),
+            name: tool.display_name(),
+            reason: String::new(),
+        };
+        let launch_task = Task::perform(tool.perform(), move |result| match result {
+            Ok(()) => cosmic::action::none(),
+            Err(e) => cosmic::action::app(Message::LaunchFailed(LaunchFailure {
+                reason: e.to_string(),
+                ..failure
+            })),
+        });
+
         if let Some(p) = self.popup.take() {
-            return destroy_popup(p);
+            return Task::batch(vec![launch_task, destroy_popup(p)]);
         }
-        Task::none()
+        launch_task
     }
 
-    fn handle_zbus_result(&self, result: Result<(), zbus::Error>) -> Task<Message> {
+    fn handle_zbus_result(&mut self, result: Result<(), zbus::Error>) -> Task<Message> {
         if let Err(e) = result {
             log::error!("cosmic-ext-classic-menu ERROR: '{}'", e);
+            self.error_message = Some(fl!("power-action-failed", reason = e.to_string()));
         }
 
         Task::none()
     }
 
     fn view_main_menu(&self) -> Element<'_, Message> {
+        if self.show_advanced_restart {
+            return AppletMenu::view_advanced_restart(&self, &self.restart_targets);
+        }
+
+        if let Some(restart_time) = &self.power_schedule_input {
+            return AppletMenu::view_power_schedule(&self, restart_time);
+        }
+
+        if let Some(inhibited) = &self.inhibited_power_action {
+            return AppletMenu::view_power_inhibitors(&self, inhibited);
+        }
+
+        if let Some(pending) = &self.pending_power_action {
+            return AppletMenu::view_power_confirmation(&self, pending);
+        }
+
         // TODO: Implement grid view
         AppletMenu::view_main_menu_list(&self)
     }
 
     fn view_context_menu(&self) -> Element<'_, Message> {
-        let context_menu = column![
-            cosmic::applet::menu_button(
-                row![cosmic::widget::text::body(fl!("settings")),].align_y(Alignment::Center)
-            )
-            .class(cosmic::theme::Button::AppletMenu)
-            .on_press(Message::LaunchTool(SystemTool::AppletSettings)),
-            cosmic::applet::padded_control(cosmic::widget::divider::horizontal::default()),
-            cosmic::applet::menu_button(
-                row![cosmic::widget::text::body(fl!("settings-label")),].align_y(Alignment::Center)
-            )
-            .class(cosmic::theme::Button::AppletMenu)
-            .on_press(Message::LaunchTool(SystemTool::SystemSettings)),
-            cosmic::applet::menu_button(
-                row![cosmic::widget::text::body(fl!("system-monitor-label")),]
-                    .align_y(Alignment::Center)
-            )
-            .class(cosmic::theme::Button::AppletMenu)
-            .on_press(Message::LaunchTool(SystemTool::SystemMonitor)),
-            cosmic::applet::menu_button(
-                row![cosmic::widget::text::body(fl!("disks-label")),].align_y(Alignment::Center)
-            )
-            .class(cosmic::theme::Button::AppletMenu)
-            .on_press(Message::LaunchTool(SystemTool::DiskManagement)),
-        ]
+        let mut context_menu = column![cosmic::applet::menu_button(
+            row![cosmic::widget::text::body(fl!("settings")),].align_y(Alignment::Center)
+        )
+        .class(cosmic::theme::Button::AppletMenu)
+        .on_press(Message::LaunchTool(SystemTool::AppletSettings)),]
         .padding([8, 0]);
 
+        if !self.available_system_tools.is_empty() {
+            context_menu = context_menu.push(cosmic::applet::padded_control(
+                cosmic::widget::divider::horizontal::default(),
+            ));
+        }
+
+        for tool in &self.available_system_tools {
+            context_menu = context_menu.push(
+                cosmic::applet::menu_button(
+                    row![cosmic::widget::text::body(tool.display_name()),]
+                        .align_y(Alignment::Center),
+                )
+                .class(cosmic::theme::Button::AppletMenu)
+                .on_press(Message::LaunchTool(tool.clone())),
+            );
+        }
+
         self.core.applet.popup_container(context_menu).into()
     }
 }
@@ -11,6 +11,8 @@ use crate::applet::{Applet, Message, PopupType};
 
 static AUTOSIZE_MAIN_ID: Lazy<cosmic::widget::Id> =
     Lazy::new(|| cosmic::widget::Id::new("autosize-main"));
+static AUTOSIZE_BADGE_ID: Lazy<cosmic::widget::Id> =
+    Lazy::new(|| cosmic::widget::Id::new("autosize-badge"));
 
 const BUTTON_DEFAULT_ICON: &[u8] =
     include_bytes!("../../res/icons/bundled/applet-button/default.svg");
@@ -132,4 +134,38 @@ impl AppletButton {
         )
         .into()
     }
+
+    /// Adds the time left until a scheduled shutdown or restart next to the applet button.
+    ///
+    /// The button is returned unchanged when nothing is scheduled or the countdown is
+    /// disabled in the settings.
+    pub fn with_scheduled_shutdown_badge<'a>(
+        applet: &'a Applet,
+        button: Element<'a, Message>,
+    ) -> Element<'a, Message> {
+        let Some(scheduled) = applet
+            .scheduled_shutdown
+            .filter(|_| applet.config.show_scheduled_shutdown_countdown)
+        else {
+            return button;
+        };
+
+        let remaining = (scheduled.at - chrono::Local::now()).num_seconds().max(0);
+        let countdown = if remaining >= 3600 {
+            format!(
+                "{}:{:02}:{:02}",
+                remaining / 3600,
+                remaining % 3600 / 60,
+                remaining % 60
+            )
+        } else {
+            format!("{}:{:02}", remaining / 60, remaining % 60)
+        };
+
+        cosmic::widget::autosize::autosize(
+            row!(button, applet.core.applet.text(countdown)).align_y(Alignment::Center),
+            AUTOSIZE_BADGE_ID.clone(),
+        )
+        .into()
+    }
 }
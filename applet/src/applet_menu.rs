@@ -1,18 +1,30 @@
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use cosmic::cosmic_theme::Spacing;
+use cosmic::iced::widget::{rich_text, span, text::Span};
 use cosmic::iced::{
     widget::{column, row},
     Alignment, Length,
 };
 use cosmic::iced::{ContentFit, Font, Limits};
-use cosmic::widget::{container, ListColumn};
+use cosmic::widget::{container, mouse_area, ListColumn};
 use cosmic::widget::{scrollable, text};
 use cosmic::{theme, Element};
 
-use crate::applet::{Applet, Message, PowerAction};
+use crate::applet::{
+    Applet, InhibitedPowerAction, Message, PendingPowerAction, PowerAction, SystemTool,
+};
 use crate::config::{HorizontalPosition, VerticalPosition};
 use crate::fl;
+use crate::logic::apps::{ApplicationCategory, SessionInfo};
+use crate::logic::launcher::REPEATED_FAILURE_THRESHOLD;
+use crate::logic::query::{SearchQuery, SearchScope};
+use crate::logic::search_history::recent_queries;
+use crate::model::application_entry::{ApplicationEntry, IconHandle};
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+use crate::power_options::{Capability, RestartTarget};
 
 pub struct AppletMenu;
 
@@ -59,12 +71,13 @@ impl AppletMenu {
                 row![categories_pane, vertical_spacer, app_list].padding([space_xxs, 0])
             }
         };
+        let error_banner = AppletMenu::create_error_banner(&applet);
         let menu_layout = match applet.config.search_field_position {
-            VerticalPosition::Top => {
-                column![current_user, search_field, dual_pane].padding([space_xxs, space_s])
-            }
+            VerticalPosition::Top => column![error_banner, current_user, search_field, dual_pane]
+                .padding([space_xxs, space_s]),
             VerticalPosition::Bottom => {
-                column![current_user, dual_pane, search_field].padding([space_xxs, space_s])
+                column![error_banner, current_user, dual_pane, search_field]
+                    .padding([space_xxs, space_s])
             }
         };
 
@@ -82,101 +95,573 @@ impl AppletMenu {
             .into()
     }
 
-    fn create_power_menu(_applet: &Applet) -> Element<'_, Message> {
-        container(
+    pub fn view_power_confirmation<'a>(
+        applet: &'a Applet,
+        pending: &PendingPowerAction,
+    ) -> Element<'a, Message> {
+        let Spacing {
+            space_s, space_m, ..
+        } = theme::active().cosmic().spacing;
+
+        let content = column![
+            text::title4(pending.action.display_name()),
+            text::body(pending.action.confirmation_message(pending.remaining)),
             row![
-                cosmic::widget::button::icon(cosmic::widget::icon::from_svg_bytes(
-                    AppletMenu::SYSTEM_LOGOUT_SYMBOLIC_ICON,
-                ).symbolic(true))
-                .on_press(Message::PowerOptionSelected(PowerAction::Logout)),
-                cosmic::widget::button::icon(cosmic::widget::icon::from_svg_bytes(
-                    AppletMenu::SYSTEM_SUSPEND_SYMBOLIC_ICON,
-                ).symbolic(true))
-                .on_press(Message::PowerOptionSelected(PowerAction::Suspend)),
-                cosmic::widget::button::icon(cosmic::widget::icon::from_svg_bytes(
-                    AppletMenu::SYSTEM_LOCKSCREEN_SYMBOLIC_ICON,
-                ).symbolic(true))
-                .on_press(Message::PowerOptionSelected(PowerAction::Lock)),
-                cosmic::widget::button::icon(cosmic::widget::icon::from_svg_bytes(
-                    AppletMenu::SYSTEM_REBOOT_SYMBOLIC_ICON,
-                ).symbolic(true))
-                .on_press(Message::PowerOptionSelected(PowerAction::Reboot)),
-                cosmic::widget::button::icon(cosmic::widget::icon::from_svg_bytes(
-                    AppletMenu::SYSTEM_SHUTDOWN_SYMBOLIC_ICON,
-                ).symbolic(true))
-                .on_press(Message::PowerOptionSelected(PowerAction::Shutdown)),
+                cosmic::widget::button::standard(fl!("cancel"))
+                    .on_press(Message::PowerActionCancelled),
+                cosmic::widget::button::destructive(pending.action.display_name())
+                    .on_press(Message::PowerActionConfirmed),
             ]
-            .align_y(Alignment::Center),
-        )
-        .width(Length::Fill)
-        .padding([20, 0])
-        .align_x(Alignment::Center)
-        .into()
+            .spacing(space_s),
+        ]
+        .spacing(space_m)
+        .padding(space_m)
+        .align_x(Alignment::Center);
+
+        applet.core.applet.popup_container(content).into()
     }
 
-    fn create_search_field(applet: &Applet) -> Element<'_, Message> {
+    pub fn view_advanced_restart<'a>(
+        applet: &'a Applet,
+        targets: &'a [RestartTarget],
+    ) -> Element<'a, Message> {
+        let Spacing { space_m, .. } = theme::active().cosmic().spacing;
+
+        let mut options = cosmic::widget::list_column();
+        for target in targets {
+            let label = match target {
+                RestartTarget::FirmwareSetup => fl!("firmware-setup"),
+                RestartTarget::BootLoaderEntry(entry) => {
+                    entry.trim_end_matches(".conf").to_string()
+                }
+            };
+            options = options.add(
+                cosmic::widget::button::custom(text::body(label))
+                    .on_press(Message::AdvancedRestartSelected(target.clone()))
+                    .class(cosmic::theme::Button::MenuItem)
+                    .width(Length::Fill),
+            );
+        }
+
+        let content = column![
+            text::title4(fl!("advanced-restart")),
+            scrollable(options).height(Length::Shrink),
+            cosmic::widget::button::standard(fl!("cancel")).on_press(Message::PowerActionCancelled),
+        ]
+        .spacing(space_m)
+        .padding(space_m)
+        .align_x(Alignment::Center);
+
+        applet.core.applet.popup_container(content).into()
+    }
+
+    pub fn view_power_schedule<'a>(
+        applet: &'a Applet,
+        restart_time: &'a str,
+    ) -> Element<'a, Message> {
         let Spacing {
-            space_xxs, space_s, ..
+            space_s, space_m, ..
         } = theme::active().cosmic().spacing;
 
-        cosmic::widget::search_input(fl!("search-placeholder"), &applet.search_field)
-            .on_input(Message::SearchFieldInput)
-            .always_active()
-            .width(Length::Fill)
-            .padding([space_xxs, space_s])
-            .into()
+        let mut content = vec![];
+        if applet.is_power_action_available(&PowerAction::Shutdown) {
+            let delays: Vec<Element<Message>> = [15, 30, 60, 120]
+                .into_iter()
+                .map(|minutes| {
+                    cosmic::widget::button::standard(fl!("schedule-delay", minutes = minutes))
+                        .on_press(Message::ScheduleShutdownIn(minutes))
+                        .into()
+                })
+                .collect();
+
+            content.push(text::title4(fl!("shutdown-in")).into());
+            content.push(
+                cosmic::widget::row::with_children(delays)
+                    .spacing(space_s)
+                    .into(),
+            );
+        }
+
+        if applet.is_power_action_available(&PowerAction::Reboot) {
+            let is_valid = chrono::NaiveTime::parse_from_str(restart_time.trim(), "%H:%M").is_ok();
+
+            content.push(text::title4(fl!("restart-at")).into());
+            content.push(
+                row![
+                    cosmic::widget::text_input("HH:MM", restart_time)
+                        .on_input(Message::PowerScheduleInput)
+                        .width(Length::Fixed(100.)),
+                    cosmic::widget::button::standard(fl!("schedule"))
+                        .on_press_maybe(is_valid.then_some(Message::ScheduleRestartAt)),
+                ]
+                .spacing(space_s)
+                .align_y(Alignment::Center)
+                .into(),
+            );
+        }
+
+        content.push(
+            cosmic::widget::button::standard(fl!("cancel"))
+                .on_press(Message::PowerActionCancelled)
+                .into(),
+        );
+
+        let content = cosmic::widget::column::with_children(content)
+            .spacing(space_m)
+            .padding(space_m)
+            .align_x(Alignment::Center);
+
+        applet.core.applet.popup_container(content).into()
     }
 
-    fn create_app_list(applet: &Applet) -> Element<'_, Message> {
+    pub fn view_power_inhibitors<'a>(
+        applet: &'a Applet,
+        inhibited: &'a InhibitedPowerAction,
+    ) -> Element<'a, Message> {
         let Spacing {
-            space_l, space_xl, ..
+            space_xxs,
+            space_s,
+            space_m,
+            ..
         } = theme::active().cosmic().spacing;
 
-        let app_list: ListColumn<Message> = applet.available_applications.iter().fold(
-            cosmic::widget::list_column().padding([0., 0.]),
-            |list, app| {
-                let button = cosmic::widget::button::custom(
-                    row![
-                        match app.icon.clone().unwrap_or_default() {
-                            crate::model::application_entry::IconHandle::SvgHandle(handle) =>
-                                container(
-                                    cosmic::widget::svg(handle)
-                                        .width(Length::Fixed(space_l.into()))
-                                        .height(Length::Fixed(space_l.into()))
-                                        .content_fit(ContentFit::Contain)
-                                ),
-                            crate::model::application_entry::IconHandle::RasterHandle(handle) =>
-                                container(
-                                    cosmic::widget::image(handle)
-                                        .width(Length::Fixed(space_l.into()))
-                                        .height(Length::Fixed(space_l.into()))
-                                        .content_fit(ContentFit::Contain)
-                                ),
-                        },
-                        cosmic::widget::Space::new(5, Length::Fill),
+        let inhibitors =
+            inhibited
+                .inhibitors
+                .iter()
+                .fold(cosmic::widget::list_column(), |list, inhibitor| {
+                    list.add(
                         column![
-                            text(&app.name),
-                            text(app.comment.as_deref().unwrap_or_default()).size(8.0),
+                            text::body(&inhibitor.who).font(Font {
+                                weight: cosmic::iced::font::Weight::Bold,
+                                ..Default::default()
+                            }),
+                            text::caption(&inhibitor.why),
                         ]
-                        .padding([0, 0]),
-                    ]
-                    .align_y(Alignment::Center),
+                        .spacing(space_xxs),
+                    )
+                });
+
+        let content = column![
+            text::title4(inhibited.action.display_name()),
+            text::body(fl!("power-action-inhibited")),
+            scrollable(inhibitors).height(Length::Shrink),
+            row![
+                cosmic::widget::button::standard(fl!("cancel"))
+                    .on_press(Message::PowerActionCancelled),
+                cosmic::widget::button::destructive(fl!("power-action-force"))
+                    .on_press(Message::PowerActionForced),
+            ]
+            .spacing(space_s),
+        ]
+        .spacing(space_m)
+        .padding(space_m)
+        .align_x(Alignment::Center);
+
+        applet.core.applet.popup_container(content).into()
+    }
+
+    fn create_power_menu(applet: &Applet) -> Element<'_, Message> {
+        let mut buttons: Vec<Element<Message>> = PowerAction::ALL
+            .iter()
+            .filter(|action| applet.is_power_action_available(action))
+            .map(|action| {
+                AppletMenu::create_power_button(
+                    *action,
+                    applet.power_capabilities.get(action).copied(),
                 )
-                .on_press(Message::ApplicationSelected(app.clone()))
-                .class(cosmic::theme::Button::MenuItem)
-                .width(Length::Fill)
-                .height(space_xl);
+            })
+            .collect();
+
+        let can_schedule = applet.is_power_action_available(&PowerAction::Shutdown)
+            || applet.is_power_action_available(&PowerAction::Reboot);
+        if can_schedule && applet.scheduled_shutdown.is_none() {
+            buttons.push(
+                cosmic::widget::tooltip(
+                    cosmic::widget::button::icon(cosmic::widget::icon::from_name("alarm-symbolic"))
+                        .on_press(Message::ShowPowerSchedule),
+                    text(fl!("schedule-power-action")),
+                    cosmic::widget::tooltip::Position::Top,
+                )
+                .into(),
+            );
+        }
+
+        // Hidden when neither the firmware nor the boot loader supports a restart target
+        if applet.is_power_action_available(&PowerAction::Reboot)
+            && !applet.restart_targets.is_empty()
+        {
+            buttons.push(
+                cosmic::widget::tooltip(
+                    cosmic::widget::button::icon(cosmic::widget::icon::from_name(
+                        "view-more-symbolic",
+                    ))
+                    .on_press(Message::ShowAdvancedRestart),
+                    text(fl!("advanced-restart")),
+                    cosmic::widget::tooltip::Position::Top,
+                )
+                .into(),
+            );
+        }
 
-                list.add(button)
-            },
+        let mut power_menu = vec![];
+        if let Some(scheduled) = &applet.scheduled_shutdown {
+            let time = if scheduled.at.date_naive() == chrono::Local::now().date_naive() {
+                scheduled.at.format("%H:%M").to_string()
+            } else {
+                scheduled.at.format("%a %H:%M").to_string()
+            };
+            let label = match scheduled.action {
+                PowerAction::Reboot => fl!("restart-scheduled", time = time),
+                _ => fl!("shutdown-scheduled", time = time),
+            };
+            power_menu.push(
+                row![
+                    text::body(label),
+                    cosmic::widget::button::text(fl!("cancel"))
+                        .on_press(Message::CancelScheduledShutdown),
+                ]
+                .align_y(Alignment::Center)
+                .into(),
+            );
+        }
+        power_menu.push(
+            cosmic::widget::row::with_children(buttons)
+                .align_y(Alignment::Center)
+                .into(),
         );
 
+        container(cosmic::widget::column::with_children(power_menu).align_x(Alignment::Center))
+            .width(Length::Fill)
+            .padding([20, 0])
+            .align_x(Alignment::Center)
+            .into()
+    }
+
+    fn create_power_button(
+        action: PowerAction,
+        capability: Option<Capability>,
+    ) -> Element<'static, Message> {
+        let icon_bytes = match action {
+            PowerAction::Logout => AppletMenu::SYSTEM_LOGOUT_SYMBOLIC_ICON,
+            PowerAction::Lock => AppletMenu::SYSTEM_LOCKSCREEN_SYMBOLIC_ICON,
+            PowerAction::Reboot => AppletMenu::SYSTEM_REBOOT_SYMBOLIC_ICON,
+            PowerAction::Shutdown => AppletMenu::SYSTEM_SHUTDOWN_SYMBOLIC_ICON,
+            PowerAction::Suspend
+            | PowerAction::Hibernate
+            | PowerAction::HybridSleep
+            | PowerAction::SuspendThenHibernate => AppletMenu::SYSTEM_SUSPEND_SYMBOLIC_ICON,
+            PowerAction::SwitchUser => AppletMenu::USER_IDLE_SYMBOLIC,
+        };
+
+        // The tooltip also tells apart the sleep variants, which share an icon
+        let tooltip = match capability {
+            Some(Capability::Challenge) => {
+                fl!(
+                    "power-action-requires-authentication",
+                    name = action.display_name()
+                )
+            }
+            Some(Capability::No) => {
+                fl!("power-action-not-permitted", name = action.display_name())
+            }
+            _ => action.display_name(),
+        };
+        let is_permitted = capability != Some(Capability::No);
+
+        cosmic::widget::tooltip(
+            cosmic::widget::button::icon(
+                cosmic::widget::icon::from_svg_bytes(icon_bytes).symbolic(true),
+            )
+            .on_press_maybe(is_permitted.then_some(Message::PowerOptionSelected(action))),
+            text(tooltip),
+            cosmic::widget::tooltip::Position::Top,
+        )
+        .into()
+    }
+
+    fn create_error_banner(applet: &Applet) -> Element<'_, Message> {
+        match &applet.error_message {
+            Some(message) => cosmic::widget::warning(message.as_str())
+                .on_close(Message::DismissError)
+                .into(),
+            None => cosmic::widget::Space::new(0, 0).into(),
+        }
+    }
+
+    fn create_search_field(applet: &Applet) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        let search_input =
+            cosmic::widget::search_input(fl!("search-placeholder"), &applet.search_field)
+                .on_input(Message::SearchFieldInput)
+                .on_submit(|_| Message::SearchSubmitted)
+                .always_active()
+                .width(Length::Fill)
+                .padding([space_xxs, space_s]);
+
+        match SearchQuery::parse(&applet.search_field).scope {
+            Some(scope) => row![AppletMenu::create_scope_chip(&scope), search_input]
+                .spacing(space_xxs)
+                .align_y(Alignment::Center)
+                .into(),
+            None => search_input.into(),
+        }
+    }
+
+    /// Shows the scope the search is limited to, pressing it searches everywhere again.
+    fn create_scope_chip(scope: &SearchScope) -> Element<'static, Message> {
+        let label = match scope {
+            SearchScope::Category(name) => fl!(
+                "search-scope-category",
+                category = ApplicationCategory::find(name)
+                    .map(|category| category.get_display_name())
+                    .unwrap_or_else(|| name.clone())
+            ),
+            scope => scope.provider().display_name(),
+        };
+
+        cosmic::widget::button::text(label)
+            .trailing_icon(cosmic::widget::icon::from_name("window-close-symbolic").handle())
+            .on_press(Message::ClearSearchScope)
+            .class(cosmic::theme::Button::Standard)
+            .into()
+    }
+
+    fn create_app_list(applet: &Applet) -> Element<'_, Message> {
+        let app_list: ListColumn<Message> = if applet.search_field.is_empty() {
+            let mut list = cosmic::widget::list_column().padding([0., 0.]);
+            if let Some(recent_searches) = AppletMenu::create_recent_searches(applet) {
+                list = list.add(recent_searches);
+            }
+            applet
+                .available_applications
+                .iter()
+                .fold(list, |list, app| {
+                    list.add(AppletMenu::create_app_entry(applet, app, None))
+                })
+        } else {
+            AppletMenu::create_search_results(applet)
+        };
+
         scrollable(app_list)
             .height(Length::Fill)
             .width(Length::FillPortion(5))
             .into()
     }
 
+    /// Offers the most recent queries while the search field is empty.
+    ///
+    /// The search field is always active, so it has the focus whenever the menu is open
+    /// and only its content decides whether the queries are shown.
+    fn create_recent_searches(applet: &Applet) -> Option<Element<'_, Message>> {
+        let Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+
+        let queries = recent_queries(&applet.state.search_history);
+        if queries.is_empty() {
+            return None;
+        }
+
+        let buttons = queries.into_iter().map(|query| {
+            cosmic::widget::button::text(query.clone())
+                .leading_icon(
+                    cosmic::widget::icon::from_name("document-open-recent-symbolic").handle(),
+                )
+                .on_press(Message::SearchFieldInput(query))
+                .into()
+        });
+
+        Some(
+            column![
+                text::heading(fl!("recent-searches")),
+                cosmic::widget::flex_row(buttons.collect())
+                    .row_spacing(space_xxs)
+                    .column_spacing(space_xxs),
+            ]
+            .spacing(space_xxs)
+            .into(),
+        )
+    }
+
+    /// Lists the search results grouped by provider, in the configured provider order.
+    fn create_search_results(applet: &Applet) -> ListColumn<Message> {
+        let mut list = cosmic::widget::list_column().padding([0., 0.]);
+        for group in &applet.search_results {
+            list = list.add(text::heading(&group.name));
+            for result in &group.results {
+                list = list.add(match &result.action {
+                    // Applications keep their context actions and failure indicator
+                    SearchAction::LaunchApplication(app) => {
+                        AppletMenu::create_app_entry(applet, app, Some(result))
+                    }
+                    _ => AppletMenu::create_search_result(result),
+                });
+            }
+        }
+
+        list
+    }
+
+    fn create_search_result(result: &SearchResult) -> Element<'_, Message> {
+        let Spacing { space_xl, .. } = theme::active().cosmic().spacing;
+
+        cosmic::widget::button::custom(
+            row![
+                AppletMenu::create_result_icon(&result.icon),
+                cosmic::widget::Space::new(5, Length::Fill),
+                column![
+                    AppletMenu::create_highlighted_title(&result.title, &result.highlights),
+                    text(
+                        result
+                            .matched_field
+                            .as_deref()
+                            .or(result.subtitle.as_deref())
+                            .unwrap_or_default()
+                    )
+                    .size(8.0),
+                ]
+                .padding([0, 0])
+                .width(Length::Fill),
+            ]
+            .align_y(Alignment::Center),
+        )
+        .on_press(Message::SearchResultActivated(result.action.clone()))
+        .class(cosmic::theme::Button::MenuItem)
+        .width(Length::Fill)
+        .height(space_xl)
+        .into()
+    }
+
+    /// Renders the title with the characters that matched the query in bold.
+    ///
+    /// The highlights are sorted, so they are consumed along with the characters.
+    fn create_highlighted_title(title: &str, highlights: &[usize]) -> Element<'static, Message> {
+        let mut highlights = highlights.iter().peekable();
+        let mut segments: Vec<(bool, String)> = vec![];
+        for (index, c) in title.chars().enumerate() {
+            let is_match = highlights.next_if_eq(&&index).is_some();
+            match segments.last_mut() {
+                Some((last_is_match, segment)) if *last_is_match == is_match => segment.push(c),
+                _ => segments.push((is_match, c.to_string())),
+            }
+        }
+
+        let spans: Vec<Span<'static, (), Font>> = segments
+            .into_iter()
+            .map(|(is_match, segment)| {
+                let weight = if is_match {
+                    cosmic::iced::font::Weight::Bold
+                } else {
+                    cosmic::iced::font::Weight::Normal
+                };
+                span(segment).font(Font {
+                    weight,
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        rich_text(spans).into()
+    }
+
+    fn create_result_icon(icon: &SearchIcon) -> Element<'static, Message> {
+        let Spacing { space_l, .. } = theme::active().cosmic().spacing;
+
+        match icon {
+            SearchIcon::Named(name) => container(
+                cosmic::widget::icon::from_name(name.clone())
+                    .size(space_l)
+                    .icon(),
+            )
+            .into(),
+            SearchIcon::Handle(IconHandle::SvgHandle(handle)) => container(
+                cosmic::widget::svg(handle.clone())
+                    .width(Length::Fixed(space_l.into()))
+                    .height(Length::Fixed(space_l.into()))
+                    .content_fit(ContentFit::Contain),
+            )
+            .into(),
+            SearchIcon::Handle(IconHandle::RasterHandle(handle)) => container(
+                cosmic::widget::image(handle.clone())
+                    .width(Length::Fixed(space_l.into()))
+                    .height(Length::Fixed(space_l.into()))
+                    .content_fit(ContentFit::Contain),
+            )
+            .into(),
+        }
+    }
+
+    /// Shows an application, with the matched characters emphasised when it's a search result.
+    fn create_app_entry<'a>(
+        applet: &'a Applet,
+        app: &'a Arc<ApplicationEntry>,
+        result: Option<&'a SearchResult>,
+    ) -> Element<'a, Message> {
+        let Spacing { space_xl, .. } = theme::active().cosmic().spacing;
+
+        let is_broken = applet
+            .launch_failures
+            .get(&app.id)
+            .is_some_and(|count| *count >= REPEATED_FAILURE_THRESHOLD);
+        let comment = if is_broken {
+            fl!("launch-failed-repeatedly")
+        } else if let Some(field) = result.and_then(|result| result.matched_field.clone()) {
+            field
+        } else {
+            app.comment.clone().unwrap_or_default()
+        };
+        let broken_indicator: Element<Message> = if is_broken {
+            cosmic::widget::icon::from_name("dialog-warning-symbolic")
+                .size(16)
+                .icon()
+                .into()
+        } else {
+            cosmic::widget::Space::new(0, 0).into()
+        };
+        let icon = SearchIcon::Handle(app.icon.clone().unwrap_or_default());
+
+        let button = cosmic::widget::button::custom(
+            row![
+                AppletMenu::create_result_icon(&icon),
+                cosmic::widget::Space::new(5, Length::Fill),
+                column![
+                    AppletMenu::create_highlighted_title(
+                        &app.name,
+                        result
+                            .map(|result| result.highlights.as_slice())
+                            .unwrap_or_default(),
+                    ),
+                    text(comment).size(8.0),
+                ]
+                .padding([0, 0])
+                .width(Length::Fill),
+                broken_indicator,
+            ]
+            .align_y(Alignment::Center),
+        )
+        .on_press(Message::ApplicationSelected(app.clone()))
+        .class(cosmic::theme::Button::MenuItem)
+        .width(Length::Fill)
+        .height(space_xl);
+        let button =
+            mouse_area(button).on_right_press(Message::ToggleApplicationActions(app.id.clone()));
+
+        if applet.expanded_application.as_ref() == Some(&app.id) {
+            let actions = row![
+                cosmic::widget::Space::new(Length::Fill, Length::Shrink),
+                cosmic::widget::button::text(fl!("run-in-terminal"))
+                    .on_press(Message::RunInTerminal(app.clone())),
+            ]
+            .align_y(Alignment::Center);
+
+            column![button, actions].into()
+        } else {
+            button.into()
+        }
+    }
+
     fn create_categories_pane(applet: &Applet) -> Element<'_, Message> {
         let Spacing { space_m, .. } = cosmic::theme::active().cosmic().spacing;
 
@@ -250,14 +735,16 @@ impl AppletMenu {
                 };
 
             let nametag_widget: Element<Message> = match &applet.config.user_widget {
-                crate::config::UserWidgetStyle::UsernamePrefered => text(&user.username)
+                crate::config::UserWidgetStyle::UsernamePrefered
+                | crate::config::UserWidgetStyle::UsernameWithSessionInfo => text(&user.username)
                     .font(Font {
                         weight: cosmic::iced::font::Weight::Bold,
                         ..Default::default()
                     })
                     .size(16)
                     .into(),
-                crate::config::UserWidgetStyle::RealNamePrefered => {
+                crate::config::UserWidgetStyle::RealNamePrefered
+                | crate::config::UserWidgetStyle::RealNameWithSessionInfo => {
                     if !&user.user_realname.is_empty() {
                         column![
                             text(&user.user_realname)
@@ -282,16 +769,56 @@ impl AppletMenu {
                 crate::config::UserWidgetStyle::None => cosmic::widget::Space::new(0, 0).into(),
             };
 
-            row![
-                profile_picture_widget,
-                cosmic::widget::Space::new(5, Length::Shrink),
-                nametag_widget
-            ]
-            .align_y(Alignment::Center)
-            .padding([10., 0.])
+            let session_info_widget: Element<Message> = match &applet.session_info {
+                Some(session_info) if applet.config.user_widget.shows_session_info() => {
+                    AppletMenu::create_session_info(session_info)
+                }
+                _ => cosmic::widget::Space::new(0, 0).into(),
+            };
+
+            cosmic::widget::button::custom(
+                row![
+                    profile_picture_widget,
+                    cosmic::widget::Space::new(5, Length::Shrink),
+                    column![nametag_widget, session_info_widget]
+                ]
+                .align_y(Alignment::Center),
+            )
+            .class(cosmic::theme::Button::MenuItem)
+            .on_press(Message::LaunchTool(SystemTool::AccountSettings))
+            .padding([10., 5.])
             .into()
         } else {
             cosmic::widget::Space::new(0, 0).into()
         }
     }
+
+    fn create_session_info(session_info: &SessionInfo) -> Element<'_, Message> {
+        let started_at = std::time::UNIX_EPOCH + Duration::from_micros(session_info.started_at);
+        let uptime_minutes = std::time::SystemTime::now()
+            .duration_since(started_at)
+            .unwrap_or_default()
+            .as_secs()
+            / 60;
+        let seat = if session_info.vtnr > 0 {
+            fl!(
+                "session-seat-vt",
+                seat = session_info.seat.clone(),
+                vt = session_info.vtnr
+            )
+        } else {
+            session_info.seat.clone()
+        };
+
+        column![
+            text::caption(&session_info.hostname),
+            text::caption(fl!(
+                "session-uptime",
+                hours = uptime_minutes / 60,
+                minutes = uptime_minutes % 60
+            )),
+            text::caption(seat),
+        ]
+        .into()
+    }
 }
@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
+use crate::applet::PowerAction;
 use crate::fl;
+use crate::logic::search::SearchProviderKind;
 use cosmic::{
     cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, Config, CosmicConfigEntry},
     Application,
@@ -18,6 +20,19 @@ pub struct AppletConfig {
     pub button_label: String,
     pub button_icon: String,
     pub recent_applications: Vec<RecentApplication>,
+    pub launch_in_scope: bool,
+    pub terminal: String,
+    pub system_tools: Vec<SystemToolEntry>,
+    pub confirm_power_actions: Vec<PowerAction>,
+    pub builtin_power_confirmation: bool,
+    pub enabled_power_actions: Vec<PowerAction>,
+    pub show_scheduled_shutdown_countdown: bool,
+    /// Enabled search providers, in the order their results are shown
+    pub search_providers: Vec<SearchProviderKind>,
+    /// Folders searched for files, the XDG user directories when empty
+    pub file_search_roots: Vec<String>,
+    /// Also match Cyrillic and Greek names by their Latin transliteration
+    pub transliterate_search: bool,
 }
 
 impl Default for AppletConfig {
@@ -30,6 +45,27 @@ impl Default for AppletConfig {
             button_label: fl!("menu-label").to_owned(),
             button_icon: format!("/usr/share/cosmic/{}/applet-buttons/default.svg", crate::applet::Applet::APP_ID).to_owned(),
             recent_applications: vec![],
+            launch_in_scope: false,
+            terminal: String::new(),
+            system_tools: SystemToolEntry::defaults(),
+            confirm_power_actions: vec![
+                PowerAction::Shutdown,
+                PowerAction::Reboot,
+                PowerAction::Logout,
+            ],
+            builtin_power_confirmation: false,
+            enabled_power_actions: vec![
+                PowerAction::SwitchUser,
+                PowerAction::Logout,
+                PowerAction::Suspend,
+                PowerAction::Lock,
+                PowerAction::Reboot,
+                PowerAction::Shutdown,
+            ],
+            show_scheduled_shutdown_countdown: true,
+            search_providers: SearchProviderKind::DEFAULT.to_vec(),
+            file_search_roots: vec![],
+            transliterate_search: true,
         }
     }
 }
@@ -48,6 +84,41 @@ impl AppletConfig {
     }
 }
 
+/// Data the applet learns while it's used, kept apart from the settings.
+#[derive(Debug, Clone, Default, CosmicConfigEntry, Eq, PartialEq)]
+#[version = 1]
+pub struct AppletState {
+    /// Queries whose results were activated, most recent first
+    pub search_history: Vec<SearchHistoryEntry>,
+    /// Commands run from the search field, most recent first
+    pub command_history: Vec<RecentCommand>,
+}
+
+impl AppletState {
+    pub fn state_handler() -> Option<Config> {
+        Config::new_state(crate::applet::Applet::APP_ID, 1).ok()
+    }
+
+    pub fn state() -> AppletState {
+        match Self::state_handler() {
+            Some(state_handler) => {
+                AppletState::get_entry(&state_handler).unwrap_or_else(|(_errs, state)| state)
+            }
+            None => AppletState::default(),
+        }
+    }
+}
+
+/// A search query whose result was activated.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SearchHistoryEntry {
+    /// The query as typed, including its scope prefix
+    pub query: String,
+    /// Application launched from the results, if the activated result was one
+    pub app_id: Option<String>,
+    pub launch_count: u32,
+}
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 
 pub enum AppletButtonStyle {
@@ -69,6 +140,20 @@ pub enum UserWidgetStyle {
     UsernamePrefered,
     RealNamePrefered,
     None,
+    /// Username, followed by the hostname, session uptime and seat
+    UsernameWithSessionInfo,
+    /// Real name, followed by the hostname, session uptime and seat
+    RealNameWithSessionInfo,
+}
+
+impl UserWidgetStyle {
+    /// Whether the hostname, session uptime and seat are shown below the name.
+    pub fn shows_session_info(&self) -> bool {
+        matches!(
+            self,
+            UserWidgetStyle::UsernameWithSessionInfo | UserWidgetStyle::RealNameWithSessionInfo
+        )
+    }
 }
 
 impl Default for UserWidgetStyle {
@@ -107,3 +192,53 @@ pub struct RecentApplication {
     pub app_id: String,
     pub launch_count: u32,
 }
+
+/// A command run from the search field.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecentCommand {
+    pub command: String,
+    pub in_terminal: bool,
+}
+
+/// An item of the context menu, pointing either to a desktop entry ID or to a command.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SystemToolEntry {
+    /// Label chosen by the user, the name is looked up when the menu is shown otherwise
+    pub label: Option<String>,
+    pub target: String,
+}
+
+impl SystemToolEntry {
+    pub fn defaults() -> Vec<SystemToolEntry> {
+        [
+            "com.system76.CosmicSettings",
+            "observatory",
+            "com.system76.CosmicFiles",
+            "com.system76.CosmicTerm",
+            "gnome-disks",
+        ]
+        .into_iter()
+        .map(|target| SystemToolEntry {
+            label: None,
+            target: target.to_owned(),
+        })
+        .collect()
+    }
+
+    /// The label chosen by the user, or the localized name of a default tool, or the
+    /// target itself.
+    pub fn display_name(&self) -> String {
+        if let Some(label) = self.label.as_ref().filter(|label| !label.is_empty()) {
+            return label.clone();
+        }
+
+        match self.target.as_str() {
+            "com.system76.CosmicSettings" => fl!("settings-label"),
+            "observatory" => fl!("system-monitor-label"),
+            "com.system76.CosmicFiles" => fl!("files-label"),
+            "com.system76.CosmicTerm" => fl!("terminal-label"),
+            "gnome-disks" => fl!("disks-label"),
+            target => target.to_owned(),
+        }
+    }
+}
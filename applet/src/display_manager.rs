@@ -0,0 +1,29 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxies for display managers
+//!
+//! Declares the calls used to bring up a login screen next to the running session.
+
+use zbus::proxy;
+
+/// GDM's factory of local displays, on the system bus.
+#[proxy(
+    interface = "org.gnome.DisplayManager.LocalDisplayFactory",
+    default_service = "org.gnome.DisplayManager",
+    default_path = "/org/gnome/DisplayManager/LocalDisplayFactory"
+)]
+pub trait GdmLocalDisplayFactory {
+    /// CreateTransientDisplay method
+    fn create_transient_display(&self) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+}
+
+/// A seat of a display manager implementing `org.freedesktop.DisplayManager`, such as LightDM.
+///
+/// The object path is taken from the `XDG_SEAT_PATH` environment variable of the session.
+#[proxy(
+    interface = "org.freedesktop.DisplayManager.Seat",
+    default_service = "org.freedesktop.DisplayManager"
+)]
+pub trait DisplayManagerSeat {
+    /// SwitchToGreeter method
+    fn switch_to_greeter(&self) -> zbus::Result<()>;
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxy for: `org.gnome.Shell.SearchProvider2`
+//!
+//! Implemented by applications offering their content to the desktop search. Bus name
+//! and object path are read from the provider's ini file, so there are no defaults.
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::OwnedValue;
+
+#[proxy(interface = "org.gnome.Shell.SearchProvider2")]
+pub trait GnomeSearchProvider {
+    /// GetInitialResultSet method
+    fn get_initial_result_set(&self, terms: &[&str]) -> zbus::Result<Vec<String>>;
+
+    /// GetSubsearchResultSet method
+    fn get_subsearch_result_set(
+        &self,
+        previous_results: &[&str],
+        terms: &[&str],
+    ) -> zbus::Result<Vec<String>>;
+
+    /// GetResultMetas method
+    fn get_result_metas(
+        &self,
+        identifiers: &[&str],
+    ) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+
+    /// ActivateResult method
+    fn activate_result(&self, identifier: &str, terms: &[&str], timestamp: u32)
+        -> zbus::Result<()>;
+
+    /// LaunchSearch method
+    fn launch_search(&self, terms: &[&str], timestamp: u32) -> zbus::Result<()>;
+}
@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxy for: `org.kde.Shutdown`
+//!
+//! Exposed by Plasma's `ksmserver` to end the session.
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "org.kde.Shutdown",
+    default_service = "org.kde.Shutdown",
+    default_path = "/Shutdown"
+)]
+pub trait KdeShutdown {
+    /// logout method, ending the session without asking for confirmation
+    ///
+    /// Unlike most D-Bus methods, its name isn't capitalized.
+    #[zbus(name = "logout")]
+    fn logout(&self) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus;
+    use zbus::interface;
+
+    /// Counts the calls of `logout`, other spellings of the method are unknown to it.
+    #[derive(Default)]
+    struct FakeKsmserver {
+        logouts: usize,
+    }
+
+    #[interface(name = "org.kde.Shutdown")]
+    impl FakeKsmserver {
+        #[zbus(name = "logout")]
+        fn logout(&mut self) {
+            self.logouts += 1;
+        }
+    }
+
+    #[tokio::test]
+    async fn calls_lowercase_logout() {
+        let (connection, service) = test_bus::serve("/Shutdown", FakeKsmserver::default()).await;
+
+        KdeShutdownProxy::new(&connection)
+            .await
+            .expect("Failed to create the proxy")
+            .logout()
+            .await
+            .expect("Failed to call logout");
+
+        let ksmserver = service
+            .object_server()
+            .interface::<_, FakeKsmserver>("/Shutdown")
+            .await
+            .expect("Failed to find the fake ksmserver");
+        assert_eq!(ksmserver.get().await.logouts, 1);
+    }
+}
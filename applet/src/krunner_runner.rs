@@ -0,0 +1,40 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxy for: `org.kde.krunner1`
+//!
+//! Implemented by KRunner plugins running as separate processes. Bus name and object
+//! path are read from the plugin's desktop file, so there are no defaults.
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::OwnedValue;
+
+/// A match as returned by `Match`: id, text, icon name, match type, relevance and
+/// properties such as `subtext` or `actions`.
+pub type RunnerMatch = (
+    String,
+    String,
+    String,
+    i32,
+    f64,
+    HashMap<String, OwnedValue>,
+);
+
+#[proxy(interface = "org.kde.krunner1")]
+pub trait KRunner {
+    /// Actions method, returns the id, text and icon name of every action
+    fn actions(&self) -> zbus::Result<Vec<(String, String, String)>>;
+
+    /// Match method
+    #[zbus(name = "Match")]
+    fn match_(&self, query: &str) -> zbus::Result<Vec<RunnerMatch>>;
+
+    /// Run method, an empty action id runs the match itself
+    fn run(&self, match_id: &str, action_id: &str) -> zbus::Result<()>;
+
+    /// Teardown method
+    fn teardown(&self) -> zbus::Result<()>;
+
+    /// Config method
+    fn config(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+}
@@ -5,6 +5,15 @@ pub mod logic;
 pub mod power_options;
 pub mod cosmic_session;
 pub mod session_manager;
+pub mod kde_shutdown;
+pub mod gnome_search_provider;
+pub mod krunner_runner;
+pub mod logind_manager;
+pub mod display_manager;
+pub mod systemd_manager;
+pub mod notifications;
 pub mod applet_button;
 pub mod applet_menu;
-pub mod model;
\ No newline at end of file
+pub mod model;
+#[cfg(test)]
+mod test_bus;
@@ -1,24 +1,123 @@
 use crate::{
-    config::{AppletConfig, RecentApplication},
+    config::{AppletConfig, RecentApplication, SearchHistoryEntry},
     fl,
+    logic::matching::Matcher,
+    logic::query::{SearchQuery, SearchScope},
+    logic::search::SearchProvider,
+    logic::search_history,
+    logind_manager::{LogindManagerProxy, LogindSessionProxy},
     model::application_entry::ApplicationEntry,
+    model::search_result::{SearchAction, SearchIcon, SearchResult},
 };
 use std::{collections::HashMap, fmt::Display, string::String, sync::Arc};
 
-use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use serde::{Deserialize, Serialize};
 
 use cosmic::{
-    iced::{stream, Subscription}, iced_futures::futures::{self, SinkExt}
+    iced::{stream, Subscription},
+    iced_futures::futures::{self, future::BoxFuture, SinkExt, StreamExt},
 };
 use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::fmt::Debug;
 use std::hash::Hash;
 use tokio::sync::mpsc;
 
-pub struct Apps;
+pub struct Apps {
+    pub transliterate: bool,
+    /// The search history, ranking applications launched for similar queries higher
+    pub history: Vec<SearchHistoryEntry>,
+}
+
+impl SearchProvider for Apps {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        let matcher = Matcher::new(self.transliterate);
+        let history = self.history.clone();
+        Box::pin(async move {
+            let category = match &query.scope {
+                Some(SearchScope::Category(name)) => match ApplicationCategory::find(name) {
+                    Some(category) => Some(category),
+                    None => return vec![],
+                },
+                _ => None,
+            };
+
+            Self::load_apps()
+                .await
+                .into_iter()
+                .filter(|app| {
+                    category
+                        .as_ref()
+                        .is_none_or(|category| app.category.iter().any(|c| c == category.mime_name))
+                })
+                .filter_map(|app| {
+                    let boost = search_history::boost(&history, &query.text, &app.id);
+                    // An empty scoped query lists everything in the scope, and apps
+                    // launched for the query before are listed even when they don't match
+                    let (score, highlights, matched_field) = if query.text.is_empty() {
+                        (0, vec![], None)
+                    } else {
+                        match Self::match_app(&matcher, &app, &query.text) {
+                            Some((score, highlights, field)) => (score + boost, highlights, field),
+                            None if boost > 0 => (boost, vec![], None),
+                            None => return None,
+                        }
+                    };
+                    Some(SearchResult {
+                        title: app.name.clone(),
+                        subtitle: app.comment.clone(),
+                        icon: SearchIcon::Handle(app.icon.clone().unwrap_or_default()),
+                        score,
+                        group: None,
+                        highlights,
+                        matched_field,
+                        action: SearchAction::LaunchApplication(app),
+                    })
+                })
+                .collect()
+        })
+    }
+}
 
 impl Apps {
+    /// Matches the name first, then the other fields describing the application.
+    ///
+    /// Returns the score, the matched characters of the name and, when something other
+    /// than the name matched, a description of that field.
+    fn match_app(
+        matcher: &Matcher,
+        app: &ApplicationEntry,
+        query: &str,
+    ) -> Option<(i64, Vec<usize>, Option<String>)> {
+        if let Some((score, highlights)) = matcher.score_with_indices(&app.name, query) {
+            return Some((score, highlights, None));
+        }
+
+        // Fields other than the name rank below any match of the name
+        let (score, field) =
+            if let Some((score, name)) = best_match(matcher, app.generic_name.as_slice(), query) {
+                (score, fl!("matched-generic-name", name = name))
+            } else if let Some((score, keyword)) = best_match(matcher, &app.keywords, query) {
+                (score, fl!("matched-keyword", keyword = keyword))
+            } else if let Some((score, word)) = app.comment.as_deref().and_then(|comment| {
+                // Word by word, as nearly any query is a subsequence of a whole sentence
+                let words: Vec<String> = comment
+                    .split_whitespace()
+                    .map(|word| {
+                        word.trim_matches(|c: char| !c.is_alphanumeric())
+                            .to_string()
+                    })
+                    .collect();
+                best_match(matcher, &words, query).map(|(score, word)| (score, word.to_string()))
+            }) {
+                (score, fl!("matched-comment", word = word))
+            } else {
+                let (score, command) = best_match(matcher, app.exec.as_slice(), query)?;
+                (score, fl!("matched-command", command = command))
+            };
+
+        Some((score / 2, vec![], Some(field)))
+    }
+
     pub async fn load_apps() -> Vec<Arc<ApplicationEntry>> {
         log::info!("Loading applications...");
         let locale = std::env::var("LANG")
@@ -36,20 +135,6 @@ impl Apps {
         all_entries
     }
 
-    pub async fn load_filtered_apps(filter: String) -> Vec<Arc<ApplicationEntry>> {
-        let matcher: SkimMatcherV2 = SkimMatcherV2::default();
-        let mut search_result: Vec<(Option<i64>, Arc<ApplicationEntry>)> = Self::load_apps()
-            .await
-            .into_iter()
-            .map(|app| (matcher.fuzzy_match(&app.name, &filter), app))
-            .filter(|app| app.0.is_some())
-            .collect();
-
-        search_result.sort_by(|a, b| b.0.cmp(&a.0));
-
-        search_result.into_iter().map(|(_, app)| app).collect()
-    }
-
     pub async fn load_app_categories() -> Vec<ApplicationCategory> {
         use std::collections::HashSet;
 
@@ -62,26 +147,11 @@ impl Apps {
             }
         }
 
-        // Všechny možné kategorie
-        const APPS_CATEGORIES: &[ApplicationCategory] = &[
-            ApplicationCategory::AUDIO,
-            ApplicationCategory::VIDEO,
-            ApplicationCategory::DEVELOPMENT,
-            ApplicationCategory::GAMES,
-            ApplicationCategory::GRAPHICS,
-            ApplicationCategory::NETWORK,
-            ApplicationCategory::OFFICE,
-            ApplicationCategory::SCIENCE,
-            ApplicationCategory::SETTINGS,
-            ApplicationCategory::SYSTEM,
-            ApplicationCategory::UTILITY,
-        ];
-
         // Vyberte pouze ty, které jsou použité
-        let mut categories = Vec::with_capacity(2 + APPS_CATEGORIES.len());
+        let mut categories = Vec::with_capacity(2 + ApplicationCategory::APPS_CATEGORIES.len());
         categories.push(ApplicationCategory::ALL);
         categories.push(ApplicationCategory::RECENTLY_USED);
-        for cat in APPS_CATEGORIES {
+        for cat in ApplicationCategory::APPS_CATEGORIES {
             if !cat.mime_name.is_empty() && used_categories.contains(&cat.mime_name.to_string()) {
                 categories.push(cat.clone());
             }
@@ -122,6 +192,14 @@ impl Apps {
     }
 }
 
+/// The best scoring of the values matching the query.
+fn best_match<'a>(matcher: &Matcher, values: &'a [String], query: &str) -> Option<(i64, &'a str)> {
+    values
+        .iter()
+        .filter_map(|value| Some((matcher.score(value, query)?, value.as_str())))
+        .max_by_key(|(score, _)| *score)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Event {
     Changed,
@@ -173,7 +251,45 @@ pub fn desktop_files<I: 'static + Hash + Copy + Send + Sync + Debug>(
     )
 }
 
+pub async fn get_session_info() -> Result<SessionInfo, zbus::Error> {
+    let conn = zbus::Connection::system().await?;
+    let manager = LogindManagerProxy::new(&conn).await?;
+    let session_path = manager.get_session_by_pid(std::process::id()).await?;
+    let session = LogindSessionProxy::builder(&conn)
+        .path(session_path)?
+        .build()
+        .await?;
+
+    let (started_at, seat, vtnr) =
+        tokio::join!(session.timestamp(), session.seat(), session.vtnr());
+
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|hostname| hostname.trim().to_string())
+        .unwrap_or_default();
+
+    Ok(SessionInfo {
+        hostname,
+        started_at: started_at?,
+        seat: seat?.0,
+        vtnr: vtnr.unwrap_or(0),
+    })
+}
+
+/// Fetches the current user from AccountsService, falling back to the passwd database.
 pub async fn get_current_user() -> Result<User, zbus::Error> {
+    match get_current_user_from_accounts().await {
+        Ok(user) => Ok(user),
+        Err(e) => {
+            log::warn!(
+                "AccountsService is unavailable, reading the passwd database: {}",
+                e
+            );
+            get_current_user_from_passwd().ok_or(e)
+        }
+    }
+}
+
+async fn get_current_user_from_accounts() -> Result<User, zbus::Error> {
     let uid = users::get_current_uid() as u64;
 
     let conn = zbus::Connection::system().await?;
@@ -199,6 +315,75 @@ pub async fn get_current_user() -> Result<User, zbus::Error> {
     })
 }
 
+/// Looks up the current user through NSS, the real name is taken from the GECOS field.
+fn get_current_user_from_passwd() -> Option<User> {
+    let user = match nix::unistd::User::from_uid(nix::unistd::getuid()) {
+        Ok(user) => user?,
+        Err(e) => {
+            log::warn!("Failed to look up the current user: {}", e);
+            return None;
+        }
+    };
+
+    // The GECOS field may list more than the name, e.g. "Full Name,Room,Phone"
+    let user_realname = user
+        .gecos
+        .to_string_lossy()
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    Some(User {
+        username: user.name,
+        user_realname,
+        profile_picture: String::new(),
+        uid: user.uid.as_raw() as u64,
+        user_home: user.dir.to_string_lossy().to_string(),
+        user_shell: user.shell.to_string_lossy().to_string(),
+    })
+}
+
+/// Emits the current user on startup and whenever AccountsService reports a change,
+/// e.g. after the avatar or the real name were edited in the settings.
+pub fn current_user_subscription() -> Subscription<Result<User, zbus::Error>> {
+    struct CurrentUserWatch;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<CurrentUserWatch>(),
+        stream::channel(4, move |mut output| async move {
+            _ = output.send(get_current_user().await).await;
+
+            let result: zbus::Result<()> = async {
+                let conn = zbus::Connection::system().await?;
+                let user =
+                    accounts_zbus::UserProxy::from_uid(&conn, users::get_current_uid() as u64)
+                        .await?;
+
+                let mut changes = futures::stream::select_all(vec![
+                    user.receive_changed().await?.map(|_| ()).boxed(),
+                    user.receive_icon_file_changed().await.map(|_| ()).boxed(),
+                    user.receive_real_name_changed().await.map(|_| ()).boxed(),
+                    user.receive_user_name_changed().await.map(|_| ()).boxed(),
+                ]);
+
+                while changes.next().await.is_some() {
+                    _ = output.send(get_current_user().await).await;
+                }
+
+                Ok(())
+            }
+            .await;
+
+            if let Err(e) = result {
+                log::warn!("Failed to watch the current user for changes: {}", e);
+            }
+
+            futures::future::pending().await
+        }),
+    )
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct User {
     pub username: String,
@@ -209,6 +394,17 @@ pub struct User {
     pub user_shell: String,
 }
 
+/// Details about the session, shown by the user widget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionInfo {
+    pub hostname: String,
+    /// When the session started, in microseconds since the epoch
+    pub started_at: u64,
+    pub seat: String,
+    /// Virtual terminal of the session, 0 if it isn't running on one
+    pub vtnr: u32,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ApplicationCategory {
     pub display_name: &'static str,
@@ -305,6 +501,37 @@ impl ApplicationCategory {
         mime_name: "Utility",
     };
 
+    // Všechny možné kategorie
+    pub const APPS_CATEGORIES: &'static [ApplicationCategory] = &[
+        ApplicationCategory::AUDIO,
+        ApplicationCategory::VIDEO,
+        ApplicationCategory::DEVELOPMENT,
+        ApplicationCategory::GAMES,
+        ApplicationCategory::GRAPHICS,
+        ApplicationCategory::NETWORK,
+        ApplicationCategory::OFFICE,
+        ApplicationCategory::SCIENCE,
+        ApplicationCategory::SETTINGS,
+        ApplicationCategory::SYSTEM,
+        ApplicationCategory::UTILITY,
+    ];
+
+    /// Finds the category whose `Categories` name or translated name starts with `name`,
+    /// e.g. `dev` for Development.
+    pub fn find(name: &str) -> Option<ApplicationCategory> {
+        let name = name.to_lowercase();
+        ApplicationCategory::APPS_CATEGORIES
+            .iter()
+            .find(|category| {
+                category.mime_name.to_lowercase().starts_with(&name)
+                    || category
+                        .get_display_name()
+                        .to_lowercase()
+                        .starts_with(&name)
+            })
+            .cloned()
+    }
+
     pub fn get_display_name(&self) -> String {
         match self.display_name {
             "all-applications" => fl!("all-applications"),
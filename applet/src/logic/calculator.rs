@@ -0,0 +1,596 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Evaluates arithmetic typed into the search field.
+//!
+//! Supported are the operators `+ - * / ^ %` (also `×`, `÷`, `**` and `mod`), factorials,
+//! percentages such as `12% of 340`, parentheses and the functions and constants listed in
+//! [`call_function`] and [`constant`]. Everything is computed locally on `f64` values.
+
+use cosmic::iced_futures::futures::future::BoxFuture;
+
+use crate::fl;
+use crate::logic::query::SearchQuery;
+use crate::logic::search::{SearchProvider, SearchProviderKind};
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+
+/// A query that evaluated to a number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalculatorResult {
+    /// The expression as typed, without the leading `=`
+    pub expression: String,
+    /// The formatted value
+    pub value: String,
+}
+
+pub struct Calculator;
+
+impl Calculator {
+    /// Evaluates the search query when it looks like arithmetic.
+    ///
+    /// A query scoped with `=` is always treated as an expression. Otherwise it has to
+    /// contain at least one operation, so that searching for a plain number doesn't show
+    /// a result.
+    pub async fn evaluate_query(query: SearchQuery) -> Option<CalculatorResult> {
+        let is_explicit = query.is_scoped_to(SearchProviderKind::Calculator);
+        let expression = query.text.as_str();
+
+        let (value, operations) = evaluate(expression)?;
+        if !is_explicit && operations == 0 {
+            return None;
+        }
+
+        Some(CalculatorResult {
+            expression: expression.to_string(),
+            value: format_value(value),
+        })
+    }
+}
+
+impl SearchProvider for Calculator {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        Box::pin(async move {
+            Self::evaluate_query(query)
+                .await
+                .map(|result| SearchResult {
+                    title: format!("= {}", result.value),
+                    subtitle: Some(fl!("calculator-copy", expression = result.expression)),
+                    icon: SearchIcon::Named("accessories-calculator-symbolic".to_string()),
+                    score: 0,
+                    group: None,
+                    highlights: vec![],
+                    matched_field: None,
+                    action: SearchAction::CopyToClipboard(result.value),
+                })
+                .into_iter()
+                .collect()
+        })
+    }
+}
+
+/// Evaluates an expression, returning its value and the number of operations it contains.
+pub fn evaluate(expression: &str) -> Option<(f64, usize)> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        return None;
+    }
+
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+        operations: 0,
+        depth: 0,
+    };
+    let value = parser.expression()?;
+    if parser.position != parser.tokens.len() || !value.is_finite() {
+        return None;
+    }
+
+    Some((value, parser.operations))
+}
+
+/// Formats a value without a trailing fraction for integers and at most 10 decimals otherwise.
+pub fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+
+    if value.abs() >= 1e15 || value.abs() < 1e-10 {
+        return format!("{:e}", value);
+    }
+
+    let formatted = format!("{:.10}", value);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Percent,
+    Bang,
+    Comma,
+    LeftParen,
+    RightParen,
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || c == '.' || c == '_' {
+                        if c != '_' {
+                            number.push(c);
+                        }
+                        chars.next();
+                    } else if (c == 'e' || c == 'E') && !number.contains(['e', 'E']) {
+                        // Only an exponent when followed by digits, otherwise it's Euler's number
+                        let mut lookahead = chars.clone();
+                        lookahead.next();
+                        let sign = lookahead.peek().copied().filter(|c| *c == '+' || *c == '-');
+                        if sign.is_some() {
+                            lookahead.next();
+                        }
+                        if !lookahead.peek().is_some_and(char::is_ascii_digit) {
+                            break;
+                        }
+
+                        number.push('e');
+                        chars.next();
+                        if let Some(sign) = sign {
+                            number.push(sign);
+                            chars.next();
+                        }
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Number(number.parse().ok()?));
+            }
+            c if c.is_alphabetic() || c == 'π' => {
+                let mut identifier = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == 'π' {
+                        identifier.extend(c.to_lowercase());
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Identifier(identifier));
+            }
+            _ => {
+                chars.next();
+                let token = match c {
+                    '+' => Token::Plus,
+                    '-' | '−' => Token::Minus,
+                    '*' | '×' | '·' => {
+                        if chars.peek() == Some(&'*') {
+                            chars.next();
+                            Token::Caret
+                        } else {
+                            Token::Star
+                        }
+                    }
+                    '/' | '÷' => Token::Slash,
+                    '^' => Token::Caret,
+                    '%' => Token::Percent,
+                    '!' => Token::Bang,
+                    ',' | ';' => Token::Comma,
+                    '(' | '[' => Token::LeftParen,
+                    ')' | ']' => Token::RightParen,
+                    _ => return None,
+                };
+                tokens.push(token);
+            }
+        }
+    }
+
+    Some(tokens)
+}
+
+/// Recursive descent parser evaluating the tokens as it goes.
+///
+/// ```text
+/// expression := term (("+" | "-") term)*
+/// term       := unary (("*" | "/" | "mod") unary)*
+/// unary      := ("+" | "-") unary | power
+/// power      := postfix ("^" unary)?
+/// postfix    := primary ("!" | "%" ("of" unary)?)*
+/// primary    := number | constant | function "(" arguments ")" | "(" expression ")"
+/// ```
+///
+/// Every recursion passes through `unary`, which gives up beyond [`MAX_DEPTH`] levels of
+/// nesting instead of overflowing the stack.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    operations: usize,
+    depth: usize,
+}
+
+/// Deepest nesting of signs, parentheses and exponents the parser follows.
+const MAX_DEPTH: usize = 64;
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn consume(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Identifier(identifier)) if identifier == keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expression(&mut self) -> Option<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.consume(&Token::Plus) {
+                value += self.term()?;
+            } else if self.consume(&Token::Minus) {
+                value -= self.term()?;
+            } else {
+                return Some(value);
+            }
+            self.operations += 1;
+        }
+    }
+
+    fn term(&mut self) -> Option<f64> {
+        let mut value = self.unary()?;
+        loop {
+            if self.consume(&Token::Star) {
+                value *= self.unary()?;
+            } else if self.consume(&Token::Slash) {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return None;
+                }
+                value /= divisor;
+            } else if self.consume_keyword("mod") {
+                let divisor = self.unary()?;
+                if divisor == 0.0 {
+                    return None;
+                }
+                value = value.rem_euclid(divisor);
+            } else {
+                return Some(value);
+            }
+            self.operations += 1;
+        }
+    }
+
+    fn unary(&mut self) -> Option<f64> {
+        if self.depth == MAX_DEPTH {
+            return None;
+        }
+
+        self.depth += 1;
+        let value = self.signed();
+        self.depth -= 1;
+        value
+    }
+
+    fn signed(&mut self) -> Option<f64> {
+        if self.consume(&Token::Minus) {
+            return Some(-self.unary()?);
+        }
+        if self.consume(&Token::Plus) {
+            return self.unary();
+        }
+
+        self.power()
+    }
+
+    fn power(&mut self) -> Option<f64> {
+        let base = self.postfix()?;
+        if self.consume(&Token::Caret) {
+            // Right associative, so 2^3^2 is 2^9
+            let exponent = self.unary()?;
+            self.operations += 1;
+            return Some(base.powf(exponent));
+        }
+
+        Some(base)
+    }
+
+    fn postfix(&mut self) -> Option<f64> {
+        let mut value = self.primary()?;
+        loop {
+            if self.consume(&Token::Bang) {
+                value = factorial(value)?;
+            } else if self.consume(&Token::Percent) {
+                value /= 100.0;
+                if self.consume_keyword("of") {
+                    value *= self.unary()?;
+                }
+            } else {
+                return Some(value);
+            }
+            self.operations += 1;
+        }
+    }
+
+    fn primary(&mut self) -> Option<f64> {
+        match self.next()? {
+            Token::Number(value) => Some(value),
+            Token::LeftParen => {
+                let value = self.expression()?;
+                self.consume(&Token::RightParen).then_some(value)
+            }
+            Token::Identifier(name) => {
+                if !self.consume(&Token::LeftParen) {
+                    return constant(&name);
+                }
+
+                let mut arguments = vec![];
+                if !self.consume(&Token::RightParen) {
+                    loop {
+                        arguments.push(self.expression()?);
+                        if self.consume(&Token::RightParen) {
+                            break;
+                        }
+                        if !self.consume(&Token::Comma) {
+                            return None;
+                        }
+                    }
+                }
+
+                self.operations += 1;
+                call_function(&name, &arguments)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Named constants.
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(std::f64::consts::PI),
+        "tau" => Some(std::f64::consts::TAU),
+        "e" => Some(std::f64::consts::E),
+        _ => None,
+    }
+}
+
+/// Functions, trigonometry works in radians.
+fn call_function(name: &str, arguments: &[f64]) -> Option<f64> {
+    let value = match (name, arguments) {
+        ("sqrt", [x]) if *x >= 0.0 => x.sqrt(),
+        ("cbrt", [x]) => x.cbrt(),
+        ("abs", [x]) => x.abs(),
+        ("sin", [x]) => x.sin(),
+        ("cos", [x]) => x.cos(),
+        ("tan", [x]) => x.tan(),
+        ("asin", [x]) => x.asin(),
+        ("acos", [x]) => x.acos(),
+        ("atan", [x]) => x.atan(),
+        ("sinh", [x]) => x.sinh(),
+        ("cosh", [x]) => x.cosh(),
+        ("tanh", [x]) => x.tanh(),
+        ("ln", [x]) if *x > 0.0 => x.ln(),
+        ("log", [x]) if *x > 0.0 => x.log10(),
+        ("log", [x, base]) if *x > 0.0 && *base > 0.0 => x.log(*base),
+        ("log2", [x]) if *x > 0.0 => x.log2(),
+        ("exp", [x]) => x.exp(),
+        ("floor", [x]) => x.floor(),
+        ("ceil", [x]) => x.ceil(),
+        ("round", [x]) => x.round(),
+        ("trunc", [x]) => x.trunc(),
+        ("deg", [x]) => x.to_degrees(),
+        ("rad", [x]) => x.to_radians(),
+        ("min", [first, rest @ ..]) => rest.iter().fold(*first, |a, b| a.min(*b)),
+        ("max", [first, rest @ ..]) => rest.iter().fold(*first, |a, b| a.max(*b)),
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+fn factorial(value: f64) -> Option<f64> {
+    // 170! is the largest factorial an f64 can hold
+    if value < 0.0 || value.fract() != 0.0 || value > 170.0 {
+        return None;
+    }
+
+    Some((2..=value as u64).fold(1.0, |product, n| product * n as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(expression: &str) -> Option<f64> {
+        evaluate(expression).map(|(value, _)| value)
+    }
+
+    fn assert_close(expression: &str, expected: f64) {
+        let value = value(expression).unwrap_or_else(|| panic!("{expression} didn't evaluate"));
+        assert!(
+            (value - expected).abs() < 1e-9,
+            "{expression} evaluated to {value}, expected {expected}"
+        );
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("1 + 2 * 3"), Some(7.0));
+        assert_eq!(value("(1 + 2) * 3"), Some(9.0));
+        assert_eq!(value("2 * 3 ^ 2"), Some(18.0));
+        assert_eq!(value("10 - 6 / 2"), Some(7.0));
+        assert_eq!(value("3! + 1"), Some(7.0));
+        assert_close("12% of 340", 40.8);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(value("10 - 4 - 3"), Some(3.0));
+        assert_eq!(value("100 / 10 / 5"), Some(2.0));
+        assert_eq!(value("2 ^ 3 ^ 2"), Some(512.0));
+        assert_eq!(value("2 ** 3"), Some(8.0));
+    }
+
+    #[test]
+    fn rejects_deep_nesting() {
+        assert_eq!(
+            value(&format!("{}1{}", "(".repeat(60), ")".repeat(60))),
+            Some(1.0)
+        );
+        assert_eq!(value(&format!("{}1", "-".repeat(60))), Some(1.0));
+
+        assert_eq!(value(&format!("{}1", "(".repeat(300_000))), None);
+        assert_eq!(value(&format!("{}1", "-".repeat(300_000))), None);
+        assert_eq!(value(&format!("2{}", "^2".repeat(300_000))), None);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(value("-3"), Some(-3.0));
+        assert_eq!(value("--3"), Some(3.0));
+        assert_eq!(value("2 * -3"), Some(-6.0));
+        assert_eq!(value("-2 ^ 2"), Some(-4.0));
+        assert_eq!(value("2 ^ -1"), Some(0.5));
+        assert_eq!(value("−5 + 1"), Some(-4.0));
+    }
+
+    #[test]
+    fn operator_aliases() {
+        assert_eq!(value("6 × 7"), Some(42.0));
+        assert_eq!(value("84 ÷ 2"), Some(42.0));
+        assert_eq!(value("17 mod 5"), Some(2.0));
+        assert_eq!(value("-1 mod 5"), Some(4.0));
+        assert_eq!(value("1_000 + 1"), Some(1001.0));
+        assert_eq!(value("1.5e3"), Some(1500.0));
+    }
+
+    #[test]
+    fn functions_and_constants() {
+        assert_close("sqrt(16)", 4.0);
+        assert_close("cbrt(27)", 3.0);
+        assert_close("abs(-2.5)", 2.5);
+        assert_close("sin(pi / 2)", 1.0);
+        assert_close("cos(0)", 1.0);
+        assert_close("ln(e)", 1.0);
+        assert_close("log(1000)", 3.0);
+        assert_close("log(8, 2)", 3.0);
+        assert_close("log2(1024)", 10.0);
+        assert_close("min(3, 1, 2)", 1.0);
+        assert_close("max(3, 1, 2)", 3.0);
+        assert_close("deg(π)", 180.0);
+        assert_close("tau", std::f64::consts::TAU);
+        assert_close("2 * e", 2.0 * std::f64::consts::E);
+        assert_close("SQRT(4)", 2.0);
+    }
+
+    #[test]
+    fn undefined_results() {
+        assert_eq!(value("1 / 0"), None);
+        assert_eq!(value("1 mod 0"), None);
+        assert_eq!(value("sqrt(-1)"), None);
+        assert_eq!(value("ln(0)"), None);
+        assert_eq!(value("asin(2)"), None);
+        assert_eq!(value("(-1)!"), None);
+        assert_eq!(value("171!"), None);
+        assert_eq!(value("10 ^ 400"), None);
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert_eq!(evaluate(""), None);
+        assert_eq!(evaluate("   "), None);
+        assert_eq!(evaluate("1 +"), None);
+        assert_eq!(evaluate("(1 + 2"), None);
+        assert_eq!(evaluate("1 + 2)"), None);
+        assert_eq!(evaluate("2 3"), None);
+        assert_eq!(evaluate("firefox"), None);
+        assert_eq!(evaluate("foo(1)"), None);
+        assert_eq!(evaluate("sqrt(1, 2)"), None);
+        assert_eq!(evaluate("1 & 2"), None);
+        assert_eq!(evaluate("1..2"), None);
+    }
+
+    #[test]
+    fn counts_operations() {
+        assert_eq!(evaluate("42"), Some((42.0, 0)));
+        assert_eq!(evaluate("(42)"), Some((42.0, 0)));
+        assert_eq!(evaluate("-42"), Some((-42.0, 0)));
+        assert_eq!(evaluate("pi").map(|(_, operations)| operations), Some(0));
+        assert_eq!(
+            evaluate("1 + 2 * 3").map(|(_, operations)| operations),
+            Some(2)
+        );
+        assert_eq!(
+            evaluate("sqrt(4)").map(|(_, operations)| operations),
+            Some(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn plain_numbers_are_suppressed() {
+        assert_eq!(
+            Calculator::evaluate_query(SearchQuery::parse("42")).await,
+            None
+        );
+        assert_eq!(
+            Calculator::evaluate_query(SearchQuery::parse("2048")).await,
+            None
+        );
+        assert_eq!(
+            Calculator::evaluate_query(SearchQuery::parse("=42")).await,
+            Some(CalculatorResult {
+                expression: "42".to_string(),
+                value: "42".to_string(),
+            })
+        );
+        assert_eq!(
+            Calculator::evaluate_query(SearchQuery::parse("6*7"))
+                .await
+                .map(|result| result.value),
+            Some("42".to_string())
+        );
+    }
+
+    #[test]
+    fn formats_values() {
+        assert_eq!(format_value(42.0), "42");
+        assert_eq!(format_value(-3.0), "-3");
+        assert_eq!(format_value(0.5), "0.5");
+        assert_eq!(format_value(1.0 / 3.0), "0.3333333333");
+        assert_eq!(format_value(1e20), "1e20");
+    }
+}
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Runs arbitrary commands typed into the search field.
+//!
+//! Queries scoped with `>` are always treated as a command. Other queries are only
+//! offered as a command when their first word is an executable on `PATH`.
+
+use cosmic::iced_futures::futures::future::BoxFuture;
+
+use crate::config::RecentCommand;
+use crate::fl;
+use crate::logic::launcher::parse_exec;
+use crate::logic::query::SearchQuery;
+use crate::logic::search::{SearchProvider, SearchProviderKind};
+use crate::logic::terminal::is_installed;
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+
+/// Number of commands kept in the history.
+pub const MAX_HISTORY: usize = 20;
+
+/// Maximum number of history entries suggested below the typed command.
+const MAX_SUGGESTIONS: usize = 5;
+
+pub struct Commands {
+    /// The command history, most recent first
+    pub history: Vec<RecentCommand>,
+}
+
+impl SearchProvider for Commands {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        let history = self.history.clone();
+        Box::pin(async move {
+            let is_explicit = query.is_scoped_to(SearchProviderKind::Commands);
+            let command = query.text;
+            if command.is_empty() && !is_explicit {
+                return vec![];
+            }
+            // A bare prefix lists the history
+            if command.is_empty() {
+                return history
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, entry)| history_result(entry, -(index as i64)))
+                    .collect();
+            }
+
+            let program = parse_exec(&command).into_iter().next().unwrap_or_default();
+            if !is_explicit && !is_installed(&program) {
+                return vec![];
+            }
+
+            let mut results = vec![
+                SearchResult {
+                    title: command.clone(),
+                    subtitle: Some(fl!("run-command")),
+                    icon: SearchIcon::Named("system-run-symbolic".to_string()),
+                    score: 2,
+                    group: None,
+                    highlights: vec![],
+                    matched_field: None,
+                    action: SearchAction::RunCommand(RecentCommand {
+                        command: command.clone(),
+                        in_terminal: false,
+                    }),
+                },
+                SearchResult {
+                    title: command.clone(),
+                    subtitle: Some(fl!("run-command-in-terminal")),
+                    icon: SearchIcon::Named("utilities-terminal-symbolic".to_string()),
+                    score: 1,
+                    group: None,
+                    highlights: vec![],
+                    matched_field: None,
+                    action: SearchAction::RunCommand(RecentCommand {
+                        command: command.clone(),
+                        in_terminal: true,
+                    }),
+                },
+            ];
+
+            results.extend(
+                history
+                    .into_iter()
+                    .filter(|entry| entry.command != command && entry.command.starts_with(&command))
+                    .take(MAX_SUGGESTIONS)
+                    .enumerate()
+                    .map(|(index, entry)| history_result(entry, -(index as i64))),
+            );
+
+            results
+        })
+    }
+}
+
+/// Moves the command to the front of the history, dropping the oldest entries.
+pub fn add_to_history(history: &mut Vec<RecentCommand>, command: RecentCommand) {
+    history.retain(|entry| entry.command != command.command);
+    history.insert(0, command);
+    history.truncate(MAX_HISTORY);
+}
+
+fn history_result(entry: RecentCommand, score: i64) -> SearchResult {
+    let subtitle = if entry.in_terminal {
+        fl!("run-command-in-terminal")
+    } else {
+        fl!("run-command")
+    };
+
+    SearchResult {
+        title: entry.command.clone(),
+        subtitle: Some(subtitle),
+        icon: SearchIcon::Named("document-open-recent-symbolic".to_string()),
+        score,
+        group: None,
+        highlights: vec![],
+        matched_field: None,
+        action: SearchAction::RunCommand(entry),
+    }
+}
@@ -0,0 +1,514 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Searches the names of files and folders below the configured roots.
+//!
+//! The names are collected into an in-memory index in the background, either when
+//! [`index_watcher`] starts or the first time they are searched, and no files are found
+//! until it's ready. The watcher rebuilds the index whenever one of the indexed
+//! directories changes. Hidden entries and entries excluded by `.gitignore` or `.ignore`
+//! files are skipped, and the walk stops at [`MAX_DEPTH`], [`MAX_ENTRIES`] or after
+//! [`INDEX_TIME_BUDGET`], whichever comes first. The index is matched against the query
+//! on a blocking thread, for at most [`QUERY_TIME_BUDGET`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use cosmic::iced::{stream, Subscription};
+use cosmic::iced_futures::futures::{
+    self,
+    future::{BoxFuture, Shared},
+    FutureExt, SinkExt,
+};
+use ignore::WalkBuilder;
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc;
+
+use crate::logic::apps::Event;
+use crate::logic::matching::Matcher;
+use crate::logic::query::SearchQuery;
+use crate::logic::search::SearchProvider;
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+
+/// How deep below a root the index goes.
+pub const MAX_DEPTH: usize = 6;
+
+/// Maximum number of entries kept in the index.
+pub const MAX_ENTRIES: usize = 50_000;
+
+/// Time the walk may take, the index stays incomplete when it's exceeded.
+pub const INDEX_TIME_BUDGET: Duration = Duration::from_secs(2);
+
+/// Time matching a query may take, the best matches found until then are shown.
+pub const QUERY_TIME_BUDGET: Duration = Duration::from_millis(200);
+
+/// Maximum number of files shown.
+const MAX_RESULTS: usize = 8;
+
+/// A file or folder in the index.
+#[derive(Clone, Debug)]
+pub struct IndexedFile {
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// The files and folders found below a set of roots.
+#[derive(Debug, Default)]
+struct FileIndex {
+    files: Vec<IndexedFile>,
+    /// Directories whose entries were listed, the ones watched for changes
+    dirs: HashSet<PathBuf>,
+}
+
+struct CachedIndex {
+    roots: Vec<PathBuf>,
+    index: Arc<FileIndex>,
+}
+
+type IndexBuild = Shared<BoxFuture<'static, Arc<FileIndex>>>;
+
+static INDEX: Lazy<RwLock<Option<CachedIndex>>> = Lazy::new(|| RwLock::new(None));
+
+/// Builds in flight by their roots, joined instead of walking the same roots twice.
+static BUILDS: Lazy<Mutex<HashMap<Vec<PathBuf>, IndexBuild>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub struct FileSearch {
+    pub roots: Vec<PathBuf>,
+    pub transliterate: bool,
+}
+
+impl SearchProvider for FileSearch {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        let roots = self.roots.clone();
+        let transliterate = self.transliterate;
+        Box::pin(async move {
+            let query = query.text;
+            let Some(index) = cached_index(&roots) else {
+                // The search is repeated by the watcher once the index is ready
+                drop(build_index(roots));
+                return vec![];
+            };
+
+            let deadline = Instant::now() + QUERY_TIME_BUDGET;
+            tokio::task::spawn_blocking(move || {
+                best_matches(&index, &query, &Matcher::new(transliterate), deadline)
+            })
+            .await
+            .unwrap_or_default()
+        })
+    }
+}
+
+/// Matches the names in the index, stopping at the deadline with the matches found so far.
+fn best_matches(
+    index: &FileIndex,
+    query: &str,
+    matcher: &Matcher,
+    deadline: Instant,
+) -> Vec<SearchResult> {
+    let mut matches: Vec<(i64, Vec<usize>, &IndexedFile)> = vec![];
+    for (position, file) in index.files.iter().enumerate() {
+        // Reading the clock for every name would slow the scan down
+        if position % 1024 == 0 && Instant::now() >= deadline {
+            log::debug!(
+                "File search stopped after {} of {} entries",
+                position,
+                index.files.len()
+            );
+            break;
+        }
+        if let Some((score, highlights)) = matcher.score_with_indices(&file.name, query) {
+            matches.push((score, highlights, file));
+        }
+    }
+    matches.sort_by(|a, b| b.0.cmp(&a.0));
+    matches.truncate(MAX_RESULTS);
+
+    matches
+        .into_iter()
+        .map(|(score, highlights, file)| SearchResult {
+            title: file.name.clone(),
+            subtitle: file.path.parent().map(display_path),
+            icon: SearchIcon::Named(icon_name(file).to_string()),
+            score,
+            group: None,
+            highlights,
+            matched_field: None,
+            action: SearchAction::OpenPath(file.path.clone()),
+        })
+        .collect()
+}
+
+/// Resolves the configured roots, falling back to the XDG user directories.
+pub fn search_roots(configured: &[String]) -> Vec<PathBuf> {
+    if !configured.is_empty() {
+        let home = dirs::home_dir();
+        return configured
+            .iter()
+            .map(|root| match (root.strip_prefix("~/"), &home) {
+                (Some(relative), Some(home)) => home.join(relative),
+                _ => PathBuf::from(root),
+            })
+            .collect();
+    }
+
+    let mut roots: Vec<PathBuf> = vec![];
+    let user_dirs = [
+        dirs::desktop_dir(),
+        dirs::document_dir(),
+        dirs::download_dir(),
+        dirs::audio_dir(),
+        dirs::picture_dir(),
+        dirs::video_dir(),
+    ];
+    for dir in user_dirs.into_iter().flatten() {
+        // Unconfigured user directories point to the home directory itself
+        if Some(&dir) != dirs::home_dir().as_ref() && !roots.contains(&dir) {
+            roots.push(dir);
+        }
+    }
+    roots
+}
+
+/// Whether the roots were indexed or are being indexed, e.g. for a query scoped with `f:`
+/// while the provider is disabled, so the index is kept up to date by [`index_watcher`].
+pub fn has_index(roots: &[PathBuf]) -> bool {
+    cached_index(roots).is_some() || BUILDS.lock().is_ok_and(|builds| builds.contains_key(roots))
+}
+
+fn cached_index(roots: &[PathBuf]) -> Option<Arc<FileIndex>> {
+    let cached = INDEX.read().ok()?;
+    cached
+        .as_ref()
+        .filter(|cached| cached.roots == roots)
+        .map(|cached| cached.index.clone())
+}
+
+/// Starts walking the roots on a blocking thread to replace the cached index, or joins
+/// the walk already running for them.
+///
+/// The walk runs to completion even when the returned future is dropped.
+fn build_index(roots: Vec<PathBuf>) -> IndexBuild {
+    let Ok(mut builds) = BUILDS.lock() else {
+        return futures::future::ready(Arc::default()).boxed().shared();
+    };
+
+    builds
+        .entry(roots.clone())
+        .or_insert_with(|| {
+            let build = async move {
+                let walk_roots = roots.clone();
+                let index = tokio::task::spawn_blocking(move || walk(&walk_roots))
+                    .await
+                    .map(Arc::new)
+                    .unwrap_or_default();
+
+                if let Ok(mut cached) = INDEX.write() {
+                    *cached = Some(CachedIndex {
+                        roots: roots.clone(),
+                        index: index.clone(),
+                    });
+                }
+                if let Ok(mut builds) = BUILDS.lock() {
+                    builds.remove(&roots);
+                }
+                index
+            }
+            .boxed()
+            .shared();
+
+            tokio::spawn(build.clone());
+            build
+        })
+        .clone()
+}
+
+fn walk(roots: &[PathBuf]) -> FileIndex {
+    let started = Instant::now();
+    let mut index = FileIndex {
+        files: vec![],
+        dirs: roots.iter().cloned().collect(),
+    };
+    let Some((first, others)) = roots.split_first() else {
+        return index;
+    };
+
+    let mut builder = WalkBuilder::new(first);
+    for root in others {
+        builder.add(root);
+    }
+    // Ignore files apply to any folder, not only to git repositories
+    builder.max_depth(Some(MAX_DEPTH)).require_git(false);
+
+    for entry in builder.build().filter_map(Result::ok) {
+        // The roots themselves aren't searched
+        if entry.depth() == 0 {
+            continue;
+        }
+        if index.files.len() >= MAX_ENTRIES {
+            log::warn!(
+                "File index is incomplete, it was limited to {} entries",
+                MAX_ENTRIES
+            );
+            break;
+        }
+        if started.elapsed() > INDEX_TIME_BUDGET {
+            log::warn!(
+                "File index is incomplete, the walk took longer than {:?} after {} entries",
+                INDEX_TIME_BUDGET,
+                index.files.len()
+            );
+            break;
+        }
+
+        let is_dir = entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_dir());
+        if is_dir && entry.depth() < MAX_DEPTH {
+            index.dirs.insert(entry.path().to_path_buf());
+        }
+        index.files.push(IndexedFile {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: entry.into_path(),
+            is_dir,
+        });
+    }
+
+    log::info!(
+        "Indexed {} files in {:?}",
+        index.files.len(),
+        started.elapsed()
+    );
+    index
+}
+
+/// Builds the index of the given roots, and rebuilds it whenever an indexed directory
+/// changes.
+pub fn index_watcher<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+    roots: Vec<PathBuf>,
+) -> Subscription<Event> {
+    Subscription::run_with_id(
+        (id, roots.clone()),
+        stream::channel(4, move |mut output| async move {
+            let (tx, mut rx) = mpsc::channel(1);
+
+            let watcher = RecommendedWatcher::new(
+                move |res: Result<notify::Event, notify::Error>| {
+                    if let Ok(event) = res {
+                        if matches!(
+                            event.kind,
+                            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                        ) {
+                            // A change is already pending when the channel is full
+                            let _ = tx.try_send(());
+                        }
+                    }
+                },
+                Config::default(),
+            );
+
+            if let Ok(mut watcher) = watcher {
+                let mut index = match cached_index(&roots) {
+                    Some(index) => index,
+                    None => {
+                        let index = build_index(roots.clone()).await;
+                        _ = output.send(Event::Changed).await;
+                        index
+                    }
+                };
+                let mut watched = HashSet::new();
+
+                loop {
+                    // Directories deeper than the index aren't watched, nor are ignored ones
+                    for dir in watched.difference(&index.dirs) {
+                        let _ = watcher.unwatch(dir);
+                    }
+                    for dir in index.dirs.difference(&watched) {
+                        let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+                    }
+                    watched = index.dirs.clone();
+
+                    if rx.recv().await.is_none() {
+                        break;
+                    }
+                    // Wait for bursts of changes, e.g. an extracted archive, to settle
+                    tokio::time::sleep(Duration::from_secs(3)).await;
+                    while rx.try_recv().is_ok() {}
+
+                    index = build_index(roots.clone()).await;
+                    _ = output.send(Event::Changed).await;
+                }
+            }
+
+            futures::future::pending().await
+        }),
+    )
+}
+
+/// Replaces the home directory with `~`.
+fn display_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Picks a generic MIME type icon based on the file extension.
+fn icon_name(file: &IndexedFile) -> &'static str {
+    if file.is_dir {
+        return "folder";
+    }
+
+    let extension = file
+        .path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" | "tiff" | "avif" | "heic" => {
+            "image-x-generic"
+        }
+        "mp3" | "flac" | "ogg" | "opus" | "wav" | "m4a" | "aac" => "audio-x-generic",
+        "mp4" | "mkv" | "webm" | "avi" | "mov" | "m4v" => "video-x-generic",
+        "pdf" => "application-pdf",
+        "zip" | "tar" | "gz" | "xz" | "zst" | "bz2" | "7z" | "rar" => "package-x-generic",
+        "odt" | "doc" | "docx" | "rtf" => "x-office-document",
+        "ods" | "xls" | "xlsx" | "csv" => "x-office-spreadsheet",
+        "odp" | "ppt" | "pptx" => "x-office-presentation",
+        "html" | "htm" => "text-html",
+        "sh" | "py" | "rs" | "js" | "ts" | "c" | "h" | "cpp" | "go" | "java" => "text-x-script",
+        _ => "text-x-generic",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory tree below the system's temporary directory, removed when dropped.
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, "").unwrap();
+            }
+            TempTree(root)
+        }
+
+        fn names(&self, index: &FileIndex) -> Vec<String> {
+            let mut names: Vec<String> = index
+                .files
+                .iter()
+                .map(|file| {
+                    let relative = file.path.strip_prefix(&self.0).unwrap();
+                    relative.to_string_lossy().to_string()
+                })
+                .collect();
+            names.sort();
+            names
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn skips_hidden_and_ignored_entries() {
+        let tree = TempTree::new(
+            "file-search-ignored",
+            &[
+                "notes.txt",
+                ".hidden/secret.txt",
+                ".gitignore",
+                "project/.gitignore",
+                "project/build/output.o",
+                "project/main.rs",
+                "project/main.log",
+            ],
+        );
+        std::fs::write(tree.0.join("project/.gitignore"), "build/\n*.log\n").unwrap();
+
+        let index = walk(&[tree.0.clone()]);
+
+        assert_eq!(
+            tree.names(&index),
+            ["notes.txt", "project", "project/main.rs"]
+        );
+    }
+
+    #[test]
+    fn stops_at_max_depth() {
+        let deep: String = (1..=MAX_DEPTH + 1)
+            .map(|level| format!("{level}/"))
+            .collect();
+        let tree = TempTree::new("file-search-depth", &[&format!("{deep}file")]);
+
+        let index = walk(&[tree.0.clone()]);
+
+        assert_eq!(index.files.len(), MAX_DEPTH);
+        assert!(index.files.iter().all(|file| file.is_dir));
+        // The deepest directory is indexed, but its entries aren't, so it isn't watched
+        assert_eq!(index.dirs.len(), MAX_DEPTH);
+        assert!(index.dirs.contains(&tree.0));
+    }
+
+    #[test]
+    fn walks_every_root() {
+        let first = TempTree::new("file-search-first", &["a.txt"]);
+        let second = TempTree::new("file-search-second", &["b.txt"]);
+
+        let index = walk(&[first.0.clone(), second.0.clone()]);
+
+        let mut names: Vec<&str> = index.files.iter().map(|file| file.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+        assert!(walk(&[]).files.is_empty());
+    }
+
+    #[test]
+    fn stops_matching_at_deadline() {
+        let tree = TempTree::new(
+            "file-search-deadline",
+            &["notes.txt", "notes.md", "todo.txt"],
+        );
+        let index = walk(&[tree.0.clone()]);
+        let matcher = Matcher::new(false);
+
+        let later = Instant::now() + Duration::from_secs(60);
+        let mut titles: Vec<String> = best_matches(&index, "notes", &matcher, later)
+            .into_iter()
+            .map(|result| result.title)
+            .collect();
+        titles.sort();
+        assert_eq!(titles, ["notes.md", "notes.txt"]);
+
+        assert!(best_matches(&index, "notes", &matcher, Instant::now()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn joins_running_build() {
+        let tree = TempTree::new("file-search-build", &["a.txt"]);
+        let roots = vec![tree.0.clone()];
+
+        let (first, second) = tokio::join!(build_index(roots.clone()), build_index(roots.clone()));
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(Arc::ptr_eq(&cached_index(&roots).unwrap(), &first));
+    }
+}
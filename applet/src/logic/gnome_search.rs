@@ -0,0 +1,464 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Queries applications implementing the GNOME Shell search provider D-Bus interface.
+//!
+//! Providers are declared by ini files in the `gnome-shell/search-providers` directories
+//! of `XDG_DATA_DIRS`, e.g. by Nautilus, Calculator or Characters. They are discovered
+//! once and cached until [`providers_watcher`] sees these directories change, or the
+//! desktop files of the applications change.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use cosmic::iced::{stream, Subscription};
+use cosmic::iced_futures::futures::{
+    self,
+    future::{join_all, BoxFuture},
+    SinkExt,
+};
+use cosmic::widget::image::Handle;
+use freedesktop_desktop_entry::DesktopEntry;
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc;
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::gnome_search_provider::GnomeSearchProviderProxy;
+use crate::logic::apps::Event;
+use crate::logic::query::SearchQuery;
+use crate::logic::search::{xdg_data_dirs, SearchProvider};
+use crate::logic::system_tools::find_desktop_file;
+use crate::model::application_entry::IconHandle;
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+
+/// Time a provider gets to answer, slower providers are left out of the results.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Maximum number of results shown per provider.
+const MAX_RESULTS: usize = 5;
+
+static PROVIDERS: Lazy<RwLock<Option<Arc<Vec<ProviderInfo>>>>> = Lazy::new(|| RwLock::new(None));
+
+/// A search provider declared by an ini file.
+#[derive(Clone, Debug)]
+pub struct ProviderInfo {
+    pub desktop_id: String,
+    pub bus_name: String,
+    pub object_path: String,
+    /// Name of the application, shown as the title of its results
+    pub name: String,
+    pub icon: Option<String>,
+}
+
+/// A result of a provider, activated by calling `ActivateResult` on it.
+#[derive(Clone, Debug)]
+pub struct GnomeShellResult {
+    /// Name of the providing application
+    pub provider_name: String,
+    pub bus_name: String,
+    pub object_path: String,
+    pub identifier: String,
+    pub terms: Vec<String>,
+}
+
+pub struct GnomeShellSearch;
+
+impl SearchProvider for GnomeShellSearch {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        Box::pin(async move {
+            let query = query.text;
+            let connection = match zbus::Connection::session().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    log::warn!("Failed to connect to the session bus: {}", e);
+                    return vec![];
+                }
+            };
+            let terms: Vec<String> = query.split_whitespace().map(str::to_string).collect();
+
+            let providers = cached_providers().await;
+            let queries = providers.iter().cloned().map(|provider| {
+                let connection = connection.clone();
+                let terms = terms.clone();
+                async move {
+                    let query = query_provider(&connection, &provider, &terms);
+                    match tokio::time::timeout(QUERY_TIMEOUT, query).await {
+                        Ok(Ok(results)) => results,
+                        Ok(Err(e)) => {
+                            log::debug!("Search provider {} failed: {}", provider.bus_name, e);
+                            vec![]
+                        }
+                        Err(_) => {
+                            log::debug!("Search provider {} timed out", provider.bus_name);
+                            vec![]
+                        }
+                    }
+                }
+            });
+
+            join_all(queries).await.into_iter().flatten().collect()
+        })
+    }
+}
+
+/// Calls `ActivateResult` on the provider of the result.
+pub async fn activate_result(result: GnomeShellResult) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let provider = GnomeSearchProviderProxy::builder(&connection)
+        .destination(result.bus_name.as_str())?
+        .path(result.object_path.as_str())?
+        .build()
+        .await?;
+    let terms: Vec<&str> = result.terms.iter().map(String::as_str).collect();
+
+    provider
+        .activate_result(&result.identifier, &terms, 0)
+        .await
+}
+
+/// Returns the cached providers, discovering them on a blocking thread on first use.
+async fn cached_providers() -> Arc<Vec<ProviderInfo>> {
+    if let Some(providers) = PROVIDERS.read().ok().and_then(|cached| cached.clone()) {
+        return providers;
+    }
+
+    let providers = tokio::task::spawn_blocking(discover_providers)
+        .await
+        .map(Arc::new)
+        .unwrap_or_default();
+    if let Ok(mut cached) = PROVIDERS.write() {
+        *cached = Some(providers.clone());
+    }
+    providers
+}
+
+/// Drops the cached providers, so they're discovered again by the next query.
+pub fn invalidate_providers() {
+    if let Ok(mut cached) = PROVIDERS.write() {
+        *cached = None;
+    }
+}
+
+/// Invalidates the cached providers whenever a provider file is added, changed or removed.
+pub fn providers_watcher<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> Subscription<Event> {
+    Subscription::run_with_id(
+        id,
+        stream::channel(4, move |mut output| async move {
+            let (tx, mut rx) = mpsc::channel(1);
+
+            let watcher = RecommendedWatcher::new(
+                move |res: Result<notify::Event, notify::Error>| {
+                    if let Ok(event) = res {
+                        if matches!(
+                            event.kind,
+                            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                        ) {
+                            // A change is already pending when the channel is full
+                            let _ = tx.try_send(());
+                        }
+                    }
+                },
+                Config::default(),
+            );
+
+            if let Ok(mut watcher) = watcher {
+                for dir in xdg_data_dirs() {
+                    let _ = watcher.watch(
+                        &dir.join("gnome-shell/search-providers"),
+                        RecursiveMode::NonRecursive,
+                    );
+                }
+
+                while rx.recv().await.is_some() {
+                    invalidate_providers();
+                    _ = output.send(Event::Changed).await;
+                }
+            }
+
+            futures::future::pending().await
+        }),
+    )
+}
+
+/// Finds the enabled providers of installed applications.
+///
+/// Files in directories listed earlier in the XDG data directories take precedence over
+/// files with the same name in later ones.
+pub fn discover_providers() -> Vec<ProviderInfo> {
+    let mut seen = HashSet::new();
+    let mut providers = vec![];
+
+    for dir in xdg_data_dirs() {
+        let Ok(entries) = std::fs::read_dir(dir.join("gnome-shell/search-providers")) else {
+            continue;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ini"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            if !seen.insert(path.file_name().map(ToOwned::to_owned)) {
+                continue;
+            }
+            if let Some(provider) = parse_provider(&path) {
+                providers.push(provider);
+            }
+        }
+    }
+
+    providers
+}
+
+/// Reads the `[Shell Search Provider]` section of a provider file.
+fn parse_provider(path: &Path) -> Option<ProviderInfo> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut keys = HashMap::new();
+    let mut in_section = false;
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == "[Shell Search Provider]";
+        } else if in_section && !line.starts_with('#') {
+            if let Some((key, value)) = line.split_once('=') {
+                keys.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    if keys.get("Version").map(String::as_str) != Some("2")
+        || keys
+            .get("DefaultDisabled")
+            .is_some_and(|value| value == "true")
+    {
+        return None;
+    }
+
+    let desktop_id = keys.remove("DesktopId")?;
+    let bus_name = keys.remove("BusName")?;
+    let object_path = keys.remove("ObjectPath")?;
+
+    // Providers of applications that aren't installed would never answer
+    let entry_path = find_desktop_file(desktop_id.trim_end_matches(".desktop"))?;
+    let locale = std::env::var("LANG")
+        .ok()
+        .and_then(|l| l.split(".").next().map(str::to_string));
+    let entry = DesktopEntry::from_path(entry_path, Some(locale.as_slice())).ok()?;
+
+    Some(ProviderInfo {
+        name: entry
+            .name(locale.as_slice())
+            .map(|name| name.into_owned())
+            .unwrap_or_else(|| desktop_id.clone()),
+        icon: entry.icon().map(str::to_string),
+        desktop_id,
+        bus_name,
+        object_path,
+    })
+}
+
+async fn query_provider(
+    connection: &zbus::Connection,
+    provider: &ProviderInfo,
+    terms: &[String],
+) -> zbus::Result<Vec<SearchResult>> {
+    let proxy = GnomeSearchProviderProxy::builder(connection)
+        .destination(provider.bus_name.as_str())?
+        .path(provider.object_path.as_str())?
+        .build()
+        .await?;
+    let term_refs: Vec<&str> = terms.iter().map(String::as_str).collect();
+
+    let mut identifiers = proxy.get_initial_result_set(&term_refs).await?;
+    identifiers.truncate(MAX_RESULTS);
+    if identifiers.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let identifier_refs: Vec<&str> = identifiers.iter().map(String::as_str).collect();
+    let metas = proxy.get_result_metas(&identifier_refs).await?;
+
+    Ok(metas
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, meta)| {
+            let identifier = string_value(&meta, "id")?;
+            let icon = string_value(&meta, "gicon")
+                .map(|gicon| icon_from_gicon(&gicon))
+                .or_else(|| provider.icon.clone().map(SearchIcon::Named))
+                .unwrap_or_else(|| SearchIcon::Named("system-search-symbolic".to_string()));
+
+            Some(SearchResult {
+                title: string_value(&meta, "name")?,
+                subtitle: string_value(&meta, "description"),
+                icon,
+                // Keep the order in which the provider returned its results
+                score: -(index as i64),
+                group: Some(provider.name.clone()),
+                highlights: vec![],
+                matched_field: None,
+                action: SearchAction::ActivateGnomeShellResult(GnomeShellResult {
+                    provider_name: provider.name.clone(),
+                    bus_name: provider.bus_name.clone(),
+                    object_path: provider.object_path.clone(),
+                    identifier,
+                    terms: terms.to_vec(),
+                }),
+            })
+        })
+        .collect())
+}
+
+fn string_value(meta: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    match meta.get(key).map(|value| &**value) {
+        Some(Value::Str(value)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Turns a serialized `GIcon`, which is either an icon name or a file, into an icon.
+fn icon_from_gicon(gicon: &str) -> SearchIcon {
+    let path = gicon.strip_prefix("file://").unwrap_or(gicon);
+    if path.starts_with('/') {
+        SearchIcon::Handle(IconHandle::RasterHandle(Handle::from(PathBuf::from(path))))
+    } else {
+        SearchIcon::Named(gicon.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus;
+    use zbus::interface;
+
+    /// Finds the documents whose name contains every term.
+    struct FakeProvider {
+        documents: Vec<(&'static str, &'static str)>,
+    }
+
+    impl FakeProvider {
+        fn search(&self, terms: &[String]) -> Vec<String> {
+            self.documents
+                .iter()
+                .filter(|(_, name)| terms.iter().all(|term| name.contains(term.as_str())))
+                .map(|(id, _)| id.to_string())
+                .collect()
+        }
+    }
+
+    #[interface(name = "org.gnome.Shell.SearchProvider2")]
+    impl FakeProvider {
+        fn get_initial_result_set(&self, terms: Vec<String>) -> Vec<String> {
+            self.search(&terms)
+        }
+
+        fn get_subsearch_result_set(
+            &self,
+            _previous_results: Vec<String>,
+            terms: Vec<String>,
+        ) -> Vec<String> {
+            self.search(&terms)
+        }
+
+        fn get_result_metas(&self, identifiers: Vec<String>) -> Vec<HashMap<String, OwnedValue>> {
+            identifiers
+                .into_iter()
+                .filter_map(|id| {
+                    let (_, name) = self.documents.iter().find(|(doc, _)| *doc == id)?;
+                    Some(HashMap::from([
+                        (
+                            "id".to_string(),
+                            OwnedValue::from(zbus::zvariant::Str::from(id)),
+                        ),
+                        (
+                            "name".to_string(),
+                            OwnedValue::from(zbus::zvariant::Str::from(*name)),
+                        ),
+                    ]))
+                })
+                .collect()
+        }
+
+        fn activate_result(&self, _identifier: String, _terms: Vec<String>, _timestamp: u32) {}
+
+        fn launch_search(&self, _terms: Vec<String>, _timestamp: u32) {}
+    }
+
+    fn provider_info() -> ProviderInfo {
+        ProviderInfo {
+            desktop_id: "org.example.Documents.desktop".to_string(),
+            bus_name: "org.example.Documents".to_string(),
+            object_path: "/org/example/Documents/SearchProvider".to_string(),
+            name: "Documents".to_string(),
+            icon: Some("org.example.Documents".to_string()),
+        }
+    }
+
+    async fn search(
+        documents: Vec<(&'static str, &'static str)>,
+        terms: &[&str],
+    ) -> Vec<SearchResult> {
+        let provider = provider_info();
+        let (connection, _service) = test_bus::serve(
+            "/org/example/Documents/SearchProvider",
+            FakeProvider { documents },
+        )
+        .await;
+        let terms: Vec<String> = terms.iter().map(|term| term.to_string()).collect();
+
+        query_provider(&connection, &provider, &terms)
+            .await
+            .expect("Failed to query the fake provider")
+    }
+
+    #[tokio::test]
+    async fn queries_provider() {
+        let documents = vec![
+            ("1", "fire safety"),
+            ("2", "campfire songs"),
+            ("3", "water polo"),
+        ];
+        let results = search(documents, &["fire"]).await;
+
+        let titles: Vec<&str> = results.iter().map(|result| result.title.as_str()).collect();
+        assert_eq!(titles, ["fire safety", "campfire songs"]);
+        // The provider's order is kept
+        assert!(results[0].score > results[1].score);
+
+        let result = &results[1];
+        assert_eq!(result.group.as_deref(), Some("Documents"));
+        assert!(matches!(&result.icon, SearchIcon::Named(icon) if icon == "org.example.Documents"));
+        let SearchAction::ActivateGnomeShellResult(activation) = &result.action else {
+            panic!("Unexpected action {:?}", result.action);
+        };
+        assert_eq!(activation.identifier, "2");
+        assert_eq!(activation.terms, ["fire"]);
+        assert_eq!(activation.bus_name, "org.example.Documents");
+    }
+
+    #[tokio::test]
+    async fn limits_results() {
+        let ids = ["1", "2", "3", "4", "5", "6", "7", "8"];
+        assert!(ids.len() > MAX_RESULTS);
+        let documents = ids.into_iter().map(|id| (id, "notes")).collect();
+        let results = search(documents, &["notes"]).await;
+
+        assert_eq!(results.len(), MAX_RESULTS);
+    }
+
+    #[tokio::test]
+    async fn finds_nothing() {
+        let results = search(vec![("1", "fire safety")], &["water"]).await;
+
+        assert!(results.is_empty());
+    }
+}
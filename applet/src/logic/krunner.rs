@@ -0,0 +1,567 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Queries KRunner plugins implementing the `org.kde.krunner1` D-Bus interface.
+//!
+//! Plugins are declared by desktop files in the `krunner/dbusplugins` directories of
+//! `XDG_DATA_DIRS`. A plugin may run as several instances, in which case its service
+//! name ends with a `*` and every matching name on the bus is queried. Runners are
+//! discovered once and cached until [`runners_watcher`] sees these directories change.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use cosmic::iced::{stream, Subscription};
+use cosmic::iced_futures::futures::{
+    self,
+    future::{join_all, BoxFuture},
+    SinkExt,
+};
+use freedesktop_desktop_entry::DesktopEntry;
+use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use tokio::sync::mpsc;
+use zbus::zvariant::{OwnedValue, Value};
+
+use crate::krunner_runner::{KRunnerProxy, RunnerMatch};
+use crate::logic::apps::Event;
+use crate::logic::query::SearchQuery;
+use crate::logic::search::{xdg_data_dirs, SearchProvider};
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+
+/// Time a runner gets to answer, slower runners are left out of the results.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Maximum number of matches shown per runner.
+const MAX_RESULTS: usize = 5;
+
+static RUNNERS: Lazy<RwLock<Option<Arc<Vec<RunnerInfo>>>>> = Lazy::new(|| RwLock::new(None));
+
+/// A runner declared by a desktop file.
+#[derive(Clone, Debug)]
+pub struct RunnerInfo {
+    /// Name of the runner, shown as the title of its results
+    pub name: String,
+    pub icon: Option<String>,
+    /// Service name, may end with a `*` for runners with several instances
+    pub service: String,
+    pub object_path: String,
+    /// Queries shorter than this aren't sent to the runner
+    pub min_letter_count: usize,
+}
+
+/// A match or one of its actions, activated by calling `Run` on the runner.
+#[derive(Clone, Debug)]
+pub struct KRunnerResult {
+    /// Name of the runner
+    pub runner_name: String,
+    pub bus_name: String,
+    pub object_path: String,
+    pub match_id: String,
+    /// Empty to run the match itself
+    pub action_id: String,
+}
+
+pub struct KRunnerSearch;
+
+impl SearchProvider for KRunnerSearch {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        Box::pin(async move {
+            let query = query.text;
+            let connection = match zbus::Connection::session().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    log::warn!("Failed to connect to the session bus: {}", e);
+                    return vec![];
+                }
+            };
+
+            let runners = cached_runners().await;
+            query_runners(&connection, &runners, &query).await
+        })
+    }
+}
+
+/// Calls `Run` on the runner of the result.
+pub async fn run_result(result: KRunnerResult) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    run_match(&connection, &result).await
+}
+
+/// Returns the cached runners, discovering them on a blocking thread on first use.
+async fn cached_runners() -> Arc<Vec<RunnerInfo>> {
+    if let Some(runners) = RUNNERS.read().ok().and_then(|cached| cached.clone()) {
+        return runners;
+    }
+
+    let runners = tokio::task::spawn_blocking(discover_runners)
+        .await
+        .map(Arc::new)
+        .unwrap_or_default();
+    if let Ok(mut cached) = RUNNERS.write() {
+        *cached = Some(runners.clone());
+    }
+    runners
+}
+
+/// Drops the cached runners, so they're discovered again by the next query.
+pub fn invalidate_runners() {
+    if let Ok(mut cached) = RUNNERS.write() {
+        *cached = None;
+    }
+}
+
+/// Invalidates the cached runners whenever a plugin file is added, changed or removed.
+pub fn runners_watcher<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> Subscription<Event> {
+    Subscription::run_with_id(
+        id,
+        stream::channel(4, move |mut output| async move {
+            let (tx, mut rx) = mpsc::channel(1);
+
+            let watcher = RecommendedWatcher::new(
+                move |res: Result<notify::Event, notify::Error>| {
+                    if let Ok(event) = res {
+                        if matches!(
+                            event.kind,
+                            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                        ) {
+                            // A change is already pending when the channel is full
+                            let _ = tx.try_send(());
+                        }
+                    }
+                },
+                Config::default(),
+            );
+
+            if let Ok(mut watcher) = watcher {
+                for dir in xdg_data_dirs() {
+                    let _ = watcher.watch(
+                        &dir.join("krunner/dbusplugins"),
+                        RecursiveMode::NonRecursive,
+                    );
+                }
+
+                while rx.recv().await.is_some() {
+                    invalidate_runners();
+                    _ = output.send(Event::Changed).await;
+                }
+            }
+
+            futures::future::pending().await
+        }),
+    )
+}
+
+/// Queries every instance of the runners at once, leaving out those failing or too slow.
+async fn query_runners(
+    connection: &zbus::Connection,
+    runners: &[RunnerInfo],
+    query: &str,
+) -> Vec<SearchResult> {
+    let bus_names = list_bus_names(connection).await;
+
+    let queries = runners
+        .iter()
+        .filter(|runner| query.chars().count() >= runner.min_letter_count)
+        .flat_map(|runner| {
+            resolve_services(&runner.service, &bus_names)
+                .into_iter()
+                .map(move |bus_name| (runner, bus_name))
+        })
+        .map(|(runner, bus_name)| async move {
+            let results = query_runner(connection, runner, &bus_name, query);
+            match tokio::time::timeout(QUERY_TIMEOUT, results).await {
+                Ok(Ok(results)) => results,
+                Ok(Err(e)) => {
+                    log::debug!("KRunner plugin {} failed: {}", bus_name, e);
+                    vec![]
+                }
+                Err(_) => {
+                    log::debug!("KRunner plugin {} timed out", bus_name);
+                    vec![]
+                }
+            }
+        });
+
+    join_all(queries).await.into_iter().flatten().collect()
+}
+
+async fn run_match(connection: &zbus::Connection, result: &KRunnerResult) -> zbus::Result<()> {
+    let runner = KRunnerProxy::builder(connection)
+        .destination(result.bus_name.as_str())?
+        .path(result.object_path.as_str())?
+        .build()
+        .await?;
+
+    runner.run(&result.match_id, &result.action_id).await
+}
+
+/// Finds the enabled runners of installed plugins.
+///
+/// Files in directories listed earlier in the XDG data directories take precedence over
+/// files with the same name in later ones.
+pub fn discover_runners() -> Vec<RunnerInfo> {
+    let mut seen = HashSet::new();
+    let mut runners = vec![];
+
+    for dir in xdg_data_dirs() {
+        let Ok(entries) = std::fs::read_dir(dir.join("krunner/dbusplugins")) else {
+            continue;
+        };
+
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "desktop"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            if !seen.insert(path.file_name().map(ToOwned::to_owned)) {
+                continue;
+            }
+            if let Some(runner) = parse_runner(&path) {
+                runners.push(runner);
+            }
+        }
+    }
+
+    runners
+}
+
+fn parse_runner(path: &Path) -> Option<RunnerInfo> {
+    let locale = std::env::var("LANG")
+        .ok()
+        .and_then(|l| l.split(".").next().map(str::to_string));
+    let entry = DesktopEntry::from_path(path, Some(locale.as_slice())).ok()?;
+
+    if entry.desktop_entry("X-KDE-PluginInfo-EnabledByDefault") == Some("false") {
+        return None;
+    }
+
+    let service = entry
+        .desktop_entry("X-Plasma-DBusRunner-Service")?
+        .to_string();
+    let object_path = entry.desktop_entry("X-Plasma-DBusRunner-Path")?.to_string();
+
+    Some(RunnerInfo {
+        name: entry
+            .name(locale.as_slice())
+            .map(|name| name.into_owned())
+            .unwrap_or_else(|| service.clone()),
+        icon: entry.icon().map(str::to_string),
+        min_letter_count: entry
+            .desktop_entry("X-Plasma-Runner-Min-Letter-Count")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0),
+        service,
+        object_path,
+    })
+}
+
+async fn list_bus_names(connection: &zbus::Connection) -> Vec<String> {
+    let names = match zbus::fdo::DBusProxy::new(connection).await {
+        Ok(proxy) => proxy.list_names().await,
+        Err(e) => Err(e.into()),
+    };
+
+    match names {
+        Ok(names) => names.into_iter().map(|name| name.to_string()).collect(),
+        Err(e) => {
+            log::debug!("Failed to list bus names: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Expands a service name ending with `*` to the matching names on the bus.
+fn resolve_services(service: &str, bus_names: &[String]) -> Vec<String> {
+    match service.strip_suffix('*') {
+        Some(prefix) => bus_names
+            .iter()
+            .filter(|name| name.starts_with(prefix))
+            .cloned()
+            .collect(),
+        None => vec![service.to_string()],
+    }
+}
+
+async fn query_runner(
+    connection: &zbus::Connection,
+    runner: &RunnerInfo,
+    bus_name: &str,
+    query: &str,
+) -> zbus::Result<Vec<SearchResult>> {
+    let proxy = KRunnerProxy::builder(connection)
+        .destination(bus_name)?
+        .path(runner.object_path.as_str())?
+        .build()
+        .await?;
+
+    let mut matches = proxy.match_(query).await?;
+    matches.sort_by(|a, b| score(b).cmp(&score(a)));
+    matches.truncate(MAX_RESULTS);
+    if matches.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // Runners without actions may not implement the method at all
+    let actions: HashMap<String, (String, String)> = proxy
+        .actions()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, text, icon)| (id, (text, icon)))
+        .collect();
+
+    let result = |match_id: &str, action_id: &str| KRunnerResult {
+        runner_name: runner.name.clone(),
+        bus_name: bus_name.to_string(),
+        object_path: runner.object_path.clone(),
+        match_id: match_id.to_string(),
+        action_id: action_id.to_string(),
+    };
+    let icon = |name: &str| {
+        Some(name)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .or_else(|| runner.icon.clone())
+            .map(SearchIcon::Named)
+            .unwrap_or_else(|| SearchIcon::Named("system-search-symbolic".to_string()))
+    };
+
+    let mut results = vec![];
+    for runner_match in &matches {
+        let (id, text, icon_name, _, _, properties) = runner_match;
+        let score = score(runner_match);
+
+        results.push(SearchResult {
+            title: text.clone(),
+            subtitle: string_value(properties, "subtext"),
+            icon: icon(icon_name),
+            score,
+            group: Some(runner.name.clone()),
+            highlights: vec![],
+            matched_field: None,
+            action: SearchAction::RunKRunnerMatch(result(id, "")),
+        });
+
+        // Without an explicit list, every action of the runner applies to the match
+        let action_ids = string_list_value(properties, "actions")
+            .unwrap_or_else(|| actions.keys().cloned().collect());
+        for (index, action_id) in action_ids.iter().enumerate() {
+            let Some((action_text, action_icon)) = actions.get(action_id) else {
+                continue;
+            };
+            results.push(SearchResult {
+                title: action_text.clone(),
+                subtitle: Some(text.clone()),
+                icon: icon(action_icon),
+                // Directly below the match they belong to
+                score: score - 1 - index as i64,
+                group: Some(runner.name.clone()),
+                highlights: vec![],
+                matched_field: None,
+                action: SearchAction::RunKRunnerMatch(result(id, action_id)),
+            });
+        }
+    }
+
+    Ok(results)
+}
+
+/// Ranks by match type first and relevance second, leaving room for the actions.
+fn score(runner_match: &RunnerMatch) -> i64 {
+    let (_, _, _, match_type, relevance, _) = runner_match;
+    *match_type as i64 * 10_000 + (relevance.clamp(0.0, 1.0) * 1000.0) as i64 * 10
+}
+
+fn string_value(properties: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    match properties.get(key).map(|value| &**value) {
+        Some(Value::Str(value)) if !value.is_empty() => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn string_list_value(properties: &HashMap<String, OwnedValue>, key: &str) -> Option<Vec<String>> {
+    match properties.get(key).map(|value| &**value) {
+        Some(Value::Array(values)) => Some(
+            values
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Str(value) => Some(value.to_string()),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus;
+    use zbus::interface;
+
+    const RUNNER_PATH: &str = "/org/example/Runner";
+
+    /// Answers every query with the same matches, after a delay.
+    struct FakeRunner {
+        matches: Vec<(&'static str, &'static str, f64, &'static [&'static str])>,
+        delay: Duration,
+        runs: Vec<(String, String)>,
+    }
+
+    impl FakeRunner {
+        fn new(matches: Vec<(&'static str, &'static str, f64, &'static [&'static str])>) -> Self {
+            Self {
+                matches,
+                delay: Duration::ZERO,
+                runs: vec![],
+            }
+        }
+    }
+
+    #[interface(name = "org.kde.krunner1")]
+    impl FakeRunner {
+        fn actions(&self) -> Vec<(String, String, String)> {
+            vec![
+                (
+                    "copy".to_string(),
+                    "Copy".to_string(),
+                    "edit-copy".to_string(),
+                ),
+                ("open".to_string(), "Open".to_string(), String::new()),
+            ]
+        }
+
+        #[zbus(name = "Match")]
+        async fn match_(&self, _query: String) -> Vec<RunnerMatch> {
+            tokio::time::sleep(self.delay).await;
+
+            self.matches
+                .iter()
+                .map(|(id, text, relevance, actions)| {
+                    let actions: Vec<String> = actions.iter().map(|a| a.to_string()).collect();
+                    let properties = HashMap::from([
+                        (
+                            "subtext".to_string(),
+                            OwnedValue::from(zbus::zvariant::Str::from(format!("About {text}"))),
+                        ),
+                        (
+                            "actions".to_string(),
+                            OwnedValue::try_from(Value::from(actions)).unwrap(),
+                        ),
+                    ]);
+                    (
+                        id.to_string(),
+                        text.to_string(),
+                        String::new(),
+                        100,
+                        *relevance,
+                        properties,
+                    )
+                })
+                .collect()
+        }
+
+        fn run(&mut self, match_id: String, action_id: String) {
+            self.runs.push((match_id, action_id));
+        }
+    }
+
+    fn runner_info() -> RunnerInfo {
+        RunnerInfo {
+            name: "Dictionary".to_string(),
+            icon: Some("accessories-dictionary".to_string()),
+            service: "org.example.Runner".to_string(),
+            object_path: RUNNER_PATH.to_string(),
+            min_letter_count: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn maps_matches() {
+        let runner = FakeRunner::new(vec![
+            ("apple", "Apple", 0.5, &["copy"]),
+            ("apricot", "Apricot", 0.9, &[]),
+        ]);
+        let (connection, _service) = test_bus::serve(RUNNER_PATH, runner).await;
+
+        let results = query_runners(&connection, &[runner_info()], "ap").await;
+        assert!(results.is_empty(), "Queried below the minimum letter count");
+
+        let results = query_runners(&connection, &[runner_info()], "app").await;
+        let titles: Vec<&str> = results.iter().map(|result| result.title.as_str()).collect();
+        assert_eq!(titles, ["Apricot", "Apple", "Copy"]);
+        assert!(results[0].score > results[1].score);
+        assert!(results[1].score > results[2].score);
+
+        let apple = &results[1];
+        assert_eq!(apple.subtitle.as_deref(), Some("About Apple"));
+        assert_eq!(apple.group.as_deref(), Some("Dictionary"));
+        assert!(matches!(&apple.icon, SearchIcon::Named(icon) if icon == "accessories-dictionary"));
+
+        let copy = &results[2];
+        assert_eq!(copy.subtitle.as_deref(), Some("Apple"));
+        assert!(matches!(&copy.icon, SearchIcon::Named(icon) if icon == "edit-copy"));
+        let SearchAction::RunKRunnerMatch(result) = &copy.action else {
+            panic!("Unexpected action {:?}", copy.action);
+        };
+        assert_eq!(result.bus_name, "org.example.Runner");
+        assert_eq!(result.match_id, "apple");
+        assert_eq!(result.action_id, "copy");
+    }
+
+    #[tokio::test]
+    async fn leaves_out_slow_runners() {
+        let mut runner = FakeRunner::new(vec![("apple", "Apple", 0.5, &[])]);
+        runner.delay = QUERY_TIMEOUT * 4;
+        let (connection, _service) = test_bus::serve(RUNNER_PATH, runner).await;
+
+        let started = std::time::Instant::now();
+        let results = query_runners(&connection, &[runner_info()], "apple").await;
+
+        assert!(results.is_empty());
+        assert!(started.elapsed() < QUERY_TIMEOUT * 2);
+    }
+
+    #[tokio::test]
+    async fn runs_matches() {
+        let runner = FakeRunner::new(vec![]);
+        let (connection, service) = test_bus::serve(RUNNER_PATH, runner).await;
+        let result = |action_id: &str| KRunnerResult {
+            runner_name: "Dictionary".to_string(),
+            bus_name: "org.example.Runner".to_string(),
+            object_path: RUNNER_PATH.to_string(),
+            match_id: "apple".to_string(),
+            action_id: action_id.to_string(),
+        };
+
+        run_match(&connection, &result(""))
+            .await
+            .expect("Failed to run the match");
+        run_match(&connection, &result("copy"))
+            .await
+            .expect("Failed to run the action");
+
+        let runner = service
+            .object_server()
+            .interface::<_, FakeRunner>(RUNNER_PATH)
+            .await
+            .expect("Failed to find the fake runner");
+        assert_eq!(
+            runner.get().await.runs,
+            [
+                ("apple".to_string(), String::new()),
+                ("apple".to_string(), "copy".to_string())
+            ]
+        );
+    }
+}
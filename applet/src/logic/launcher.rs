@@ -0,0 +1,379 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+
+use zbus::zvariant::Value;
+
+use crate::fl;
+use crate::logic::terminal::is_installed;
+use crate::notifications::NotificationsProxy;
+use crate::systemd_manager::SystemdManagerProxy;
+
+/// Number of consecutive failed launches after which an entry is flagged as broken.
+pub const REPEATED_FAILURE_THRESHOLD: u32 = 2;
+
+/// Describes a launch that could not be carried out.
+#[derive(Clone, Debug)]
+pub struct LaunchFailure {
+    /// Identifier of the launched entry, used to track repeated failures.
+    pub id: String,
+    /// Name of the entry as shown to the user.
+    pub name: String,
+    /// Reason of the failure.
+    pub reason: String,
+}
+
+/// Launches the `Exec` line of a desktop entry.
+///
+/// When `terminal` is set, the command is run inside the terminal emulator it names, an
+/// empty value picks the first installed one.
+///
+/// When `in_scope` is set the process is started in a transient `app-<id>-<random>.scope`
+/// unit of the systemd user manager by [`spawn_in_scope`], so that it doesn't live in the
+/// applet's cgroup. Otherwise it is started by [`spawn_detached`].
+pub async fn launch(
+    exec: String,
+    env_vars: Vec<(String, String)>,
+    app_id: Option<String>,
+    terminal: Option<String>,
+    in_scope: bool,
+) -> std::io::Result<()> {
+    let mut args = parse_exec(&exec);
+
+    if let Some(preference) = terminal {
+        let Some(prefix) = crate::logic::terminal::command_prefix(&preference) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no terminal emulator was found",
+            ));
+        };
+        args = prefix.into_iter().chain(args).collect();
+    }
+
+    if std::env::var("FLATPAK_ID").is_ok() {
+        args = ["flatpak-spawn".to_string(), "--host".to_string()]
+            .into_iter()
+            .chain(args)
+            .collect();
+    }
+
+    if in_scope {
+        spawn_in_scope(args, env_vars, app_id.as_deref()).await
+    } else {
+        spawn_detached(&args, env_vars).await.map(|_| ())
+    }
+}
+
+/// Starts a command detached from the applet, returning the PID of the new process.
+///
+/// libcosmic double-forks and calls `setsid`, so the application is neither a child of
+/// the applet nor part of its session. As the exit status of the application is never
+/// seen, a program that can't be found is reported up front.
+pub async fn spawn_detached(
+    args: &[String],
+    env_vars: Vec<(String, String)>,
+) -> std::io::Result<u32> {
+    let Some((program, program_args)) = args.split_first() else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "empty Exec line",
+        ));
+    };
+    ensure_executable(program)?;
+
+    let mut command = std::process::Command::new(program);
+    command.args(program_args).envs(env_vars);
+
+    cosmic::process::spawn(command)
+        .await
+        .ok_or_else(|| std::io::Error::other(format!("'{}' could not be started", program)))
+}
+
+/// Checks that `program` can be found.
+pub fn ensure_executable(program: &str) -> std::io::Result<()> {
+    if program.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "empty Exec line",
+        ));
+    }
+
+    which::which(program).map(|_| ()).map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("'{}' was not found", program),
+        )
+    })
+}
+
+/// Shows a desktop notification about a failed launch.
+pub async fn notify_failure(failure: LaunchFailure) {
+    if let Err(e) = send_failure_notification(&failure).await {
+        log::warn!("Failed to send launch failure notification: {}", e);
+    }
+}
+
+async fn send_failure_notification(failure: &LaunchFailure) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let notifications = NotificationsProxy::new(&connection).await?;
+
+    notifications
+        .notify(
+            &fl!("menu-label"),
+            0,
+            "dialog-error-symbolic",
+            &fl!("launch-failed-title", name = failure.name.clone()),
+            &failure.reason,
+            &[],
+            HashMap::new(),
+            -1,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Spawns the command in its own transient systemd scope.
+///
+/// `systemd-run --scope` creates the scope before it executes the command, so nothing of
+/// the application ever runs in the applet's cgroup. Without `systemd-run` the process is
+/// started by the applet and moved into the scope afterwards, so children it forks before
+/// the move stay behind in the applet's cgroup. Failing to create the scope is not fatal
+/// then, the application keeps running in the applet's cgroup.
+pub async fn spawn_in_scope(
+    args: Vec<String>,
+    env_vars: Vec<(String, String)>,
+    app_id: Option<&str>,
+) -> std::io::Result<()> {
+    let Some(program) = args.first() else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "empty Exec line",
+        ));
+    };
+    let unit = scope_unit_name(app_id.unwrap_or(program));
+
+    if is_installed("systemd-run") {
+        let systemd_run = ["systemd-run", "--user", "--scope", "--collect", "--quiet"]
+            .into_iter()
+            .map(str::to_string)
+            .chain([format!("--unit={}", unit), "--".to_string()]);
+        // systemd-run only fails once it runs, so report missing programs up front
+        ensure_executable(program)?;
+        spawn_detached(&systemd_run.chain(args).collect::<Vec<_>>(), env_vars).await?;
+    } else {
+        let pid = spawn_detached(&args, env_vars).await?;
+        if let Err(e) = move_to_scope(&unit, pid).await {
+            log::warn!("Failed to move '{}' into {}: {}", program, unit, e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn move_to_scope(unit: &str, pid: u32) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    let manager = SystemdManagerProxy::new(&connection).await?;
+    let properties = [
+        ("PIDs", Value::from(vec![pid])),
+        ("CollectMode", Value::from("inactive-or-failed")),
+    ];
+
+    manager
+        .start_transient_unit(unit, "fail", &properties, &[])
+        .await?;
+
+    Ok(())
+}
+
+/// Builds a unit name following the systemd XDG application naming convention.
+fn scope_unit_name(app_id: &str) -> String {
+    let app_id = app_id.rsplit('/').next().unwrap_or(app_id);
+    // Every `RandomState` is seeded differently, which makes the suffix unique
+    let hasher = RandomState::new().build_hasher();
+
+    format!(
+        "app-{}-{:016x}.scope",
+        escape_unit_component(app_id),
+        hasher.finish()
+    )
+}
+
+/// Escapes a string the same way `systemd-escape` does, so it can be used inside a unit name.
+fn escape_unit_component(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for (i, byte) in value.bytes().enumerate() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b':' | b'_' => escaped.push(byte as char),
+            b'.' if i > 0 => escaped.push('.'),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
+
+/// Splits an `Exec` value into arguments, dropping field codes such as `%u` or `%F`.
+pub fn parse_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut has_token = false;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            '\\' if in_quotes => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            '%' => match chars.next() {
+                Some('%') => {
+                    current.push('%');
+                    has_token = true;
+                }
+                // Field codes expand to nothing when launching without files or URLs
+                _ => {}
+            },
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+
+    if has_token {
+        args.push(current);
+    }
+
+    args
+}
+
+/// Joins arguments back into an `Exec` value, quoting them where needed.
+pub fn join_exec(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let needs_quotes = arg.is_empty()
+                || arg
+                    .chars()
+                    .any(|c| c.is_whitespace() || "\"'\\$`<>|&;*?#()".contains(c));
+            if !needs_quotes {
+                return arg.clone();
+            }
+
+            let mut quoted = String::with_capacity(arg.len() + 2);
+            quoted.push('"');
+            for c in arg.chars() {
+                if matches!(c, '"' | '`' | '$' | '\\') {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            }
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_exec_lines() {
+        assert_eq!(parse_exec("firefox %u"), args(&["firefox"]));
+        assert_eq!(parse_exec("  gimp   --new  "), args(&["gimp", "--new"]));
+        assert_eq!(parse_exec("app %F --flag"), args(&["app", "--flag"]));
+        assert_eq!(parse_exec("printf 100%%"), args(&["printf", "100%"]));
+        assert_eq!(
+            parse_exec("\"/opt/My App/app\" \"\" end"),
+            args(&["/opt/My App/app", "", "end"])
+        );
+        assert_eq!(
+            parse_exec("sh -c \"echo \\\"hi\\\" \\$HOME\""),
+            args(&["sh", "-c", "echo \"hi\" $HOME"])
+        );
+        assert!(parse_exec("").is_empty());
+        assert!(parse_exec("%U").is_empty());
+    }
+
+    #[test]
+    fn escapes_unit_components() {
+        assert_eq!(escape_unit_component("firefox"), "firefox");
+        assert_eq!(
+            escape_unit_component("org.mozilla.firefox"),
+            "org.mozilla.firefox"
+        );
+        assert_eq!(escape_unit_component("my-app"), "my\\x2dapp");
+        assert_eq!(escape_unit_component(".hidden"), "\\x2ehidden");
+        assert_eq!(escape_unit_component("a b/c"), "a\\x20b\\x2fc");
+        assert_eq!(escape_unit_component("ž"), "\\xc5\\xbe");
+    }
+
+    #[test]
+    fn names_scope_units() {
+        let unit = scope_unit_name("/usr/bin/my-app");
+        let suffix = unit
+            .strip_prefix("app-my\\x2dapp-")
+            .and_then(|rest| rest.strip_suffix(".scope"))
+            .unwrap_or_else(|| panic!("Unexpected unit name {unit}"));
+
+        assert_eq!(suffix.len(), 16);
+        assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(scope_unit_name("my-app"), scope_unit_name("my-app"));
+    }
+
+    #[test]
+    fn joins_arguments_back() {
+        let cases = [
+            args(&["firefox", "--new-window"]),
+            args(&["sh", "-c", "echo \"$HOME\" | wc -c"]),
+            args(&["/opt/My App/bin/app", "--title", "It's here"]),
+            args(&["app", "", "back\\slash", "`tick`"]),
+            args(&["env", "LANG=C", "app"]),
+        ];
+
+        for case in cases {
+            assert_eq!(parse_exec(&join_exec(&case)), case, "{}", join_exec(&case));
+        }
+    }
+
+    #[test]
+    fn quotes_only_where_needed() {
+        assert_eq!(
+            join_exec(&args(&["firefox", "-P", "work"])),
+            "firefox -P work"
+        );
+        assert_eq!(join_exec(&args(&["sh", "-c", "ls *"])), "sh -c \"ls *\"");
+        assert_eq!(join_exec(&args(&["echo", "$HOME"])), "echo \"\\$HOME\"");
+    }
+
+    #[test]
+    fn keeps_exec_lines_through_a_round_trip() {
+        let exec = "\"/opt/My App/app\" --name \"Some \\\"Title\\\"\" --flag";
+        let parsed = parse_exec(exec);
+
+        assert_eq!(
+            parsed,
+            args(&["/opt/My App/app", "--name", "Some \"Title\"", "--flag"])
+        );
+        assert_eq!(parse_exec(&join_exec(&parsed)), parsed);
+    }
+}
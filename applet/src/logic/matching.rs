@@ -0,0 +1,433 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Matches search queries against names, tolerating typos, missing diacritics and,
+//! optionally, a different script.
+//!
+//! Both sides are lowercased and stripped of diacritics, so "kalkulacka" finds
+//! "Kalkulačka". With transliteration enabled, Cyrillic and Greek are compared in their
+//! Latin form as well, so "terminal" finds "Терминал". When the fuzzy subsequence match
+//! fails, every word of the name is compared with the query by edit distance, which
+//! finds "Firefox" for "fierfox" or "LibreOffice" for "libreofice".
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+/// Score of a match with one typo, lower than any subsequence match of a similar length.
+const TYPO_SCORE: i64 = 10;
+
+pub struct Matcher {
+    skim: SkimMatcherV2,
+    transliterate: bool,
+}
+
+impl Matcher {
+    pub fn new(transliterate: bool) -> Matcher {
+        Matcher {
+            skim: SkimMatcherV2::default(),
+            transliterate,
+        }
+    }
+
+    /// Scores how well `candidate` matches `query`, higher is better.
+    pub fn score(&self, candidate: &str, query: &str) -> Option<i64> {
+        self.score_with_indices(candidate, query)
+            .map(|(score, _)| score)
+    }
+
+    /// Like [`Matcher::score`], also returning the indices of the characters of
+    /// `candidate` that matched, in ascending order.
+    pub fn score_with_indices(&self, candidate: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+        let mut best = self.match_normalized(candidate, query, false);
+        if self.transliterate {
+            let transliterated = self.match_normalized(candidate, query, true);
+            if transliterated.as_ref().map(|(score, _)| score)
+                > best.as_ref().map(|(score, _)| score)
+            {
+                best = transliterated;
+            }
+        }
+        best
+    }
+
+    fn match_normalized(
+        &self,
+        candidate: &str,
+        query: &str,
+        transliterated: bool,
+    ) -> Option<(i64, Vec<usize>)> {
+        let (candidate_text, origins) = normalize(candidate, transliterated);
+        let (query, _) = normalize(query, transliterated);
+
+        let (score, indices) = self
+            .skim
+            .fuzzy_indices(&candidate_text, &query)
+            .or_else(|| typo_match(&candidate_text, &query))?;
+
+        // Map back to the characters they were derived from, "ß" becomes "ss" for example
+        let mut indices: Vec<usize> = indices
+            .into_iter()
+            .filter_map(|index| origins.get(index).copied())
+            .collect();
+        indices.dedup();
+        Some((score, indices))
+    }
+}
+
+/// Folds and optionally transliterates the text, along with the index of the original
+/// character every resulting character comes from.
+fn normalize(text: &str, transliterated: bool) -> (String, Vec<usize>) {
+    let mut normalized = String::with_capacity(text.len());
+    let mut origins = Vec::with_capacity(text.len());
+    for (index, c) in text.chars().enumerate() {
+        let mut folded = fold(&c.to_string());
+        if transliterated {
+            folded = transliterate(&folded);
+        }
+        origins.extend(folded.chars().map(|_| index));
+        normalized.push_str(&folded);
+    }
+    (normalized, origins)
+}
+
+/// Matches the query against the start of every word of the candidate by edit distance,
+/// returning the indices of the closest part.
+fn typo_match(candidate: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    let allowed = allowed_typos(query.chars().count());
+    if allowed == 0 {
+        return None;
+    }
+
+    // Queries spanning several words are compared with the whole candidate as well
+    let chars: Vec<char> = candidate.chars().collect();
+    let mut starts = vec![0];
+    starts.extend(
+        (1..chars.len()).filter(|&i| !chars[i - 1].is_alphanumeric() && chars[i].is_alphanumeric()),
+    );
+    let length = query.chars().count();
+
+    starts
+        .into_iter()
+        .flat_map(|start| {
+            // Compare with the part of the word as long as the query, so prefixes match
+            let word_end = if start == 0 {
+                chars.len()
+            } else {
+                (start..chars.len())
+                    .find(|&i| !chars[i].is_alphanumeric())
+                    .unwrap_or(chars.len())
+            };
+            let limit = (start + length + allowed).min(word_end);
+            let first = (start + length.saturating_sub(allowed)).min(limit);
+            (first..=limit).map(move |end| (start, end))
+        })
+        .filter(|(start, end)| end > start)
+        .map(|(start, end)| {
+            let part: String = chars[start..end].iter().collect();
+            (edit_distance(&part, query), start, end)
+        })
+        .filter(|(distance, _, _)| *distance <= allowed)
+        .min_by_key(|(distance, _, _)| *distance)
+        .map(|(distance, start, end)| {
+            (
+                TYPO_SCORE * (allowed + 1 - distance) as i64,
+                (start..end).collect(),
+            )
+        })
+}
+
+/// Number of typos tolerated in a query, none for short queries as nearly anything
+/// would match them.
+fn allowed_typos(length: usize) -> usize {
+    match length {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Optimal string alignment distance, counting swapped neighbours as one edit.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+/// Lowercases the text and replaces letters with diacritics by their base letter.
+pub fn fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars().flat_map(char::to_lowercase) {
+        match c {
+            'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' | 'ā' | 'ă' | 'ą' => folded.push('a'),
+            'ç' | 'ć' | 'č' | 'ĉ' | 'ċ' => folded.push('c'),
+            'ď' | 'đ' => folded.push('d'),
+            'é' | 'è' | 'ê' | 'ë' | 'ě' | 'ē' | 'ę' | 'ė' => folded.push('e'),
+            'ğ' | 'ģ' => folded.push('g'),
+            'í' | 'ì' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => folded.push('i'),
+            'ķ' => folded.push('k'),
+            'ĺ' | 'ľ' | 'ł' | 'ļ' => folded.push('l'),
+            'ñ' | 'ń' | 'ň' | 'ņ' => folded.push('n'),
+            'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ø' | 'ō' | 'ő' => folded.push('o'),
+            'ŕ' | 'ř' => folded.push('r'),
+            'ś' | 'š' | 'ş' | 'ș' => folded.push('s'),
+            'ť' | 'ţ' | 'ț' => folded.push('t'),
+            'ú' | 'ù' | 'û' | 'ü' | 'ů' | 'ū' | 'ű' | 'ų' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'ź' | 'ž' | 'ż' => folded.push('z'),
+            'ß' => folded.push_str("ss"),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            // Greek accents and the final sigma
+            'ά' => folded.push('α'),
+            'έ' => folded.push('ε'),
+            'ή' => folded.push('η'),
+            'ί' | 'ϊ' | 'ΐ' => folded.push('ι'),
+            'ό' => folded.push('ο'),
+            'ύ' | 'ϋ' | 'ΰ' => folded.push('υ'),
+            'ώ' => folded.push('ω'),
+            'ς' => folded.push('σ'),
+            'ё' => folded.push('е'),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Transliterates lowercase Cyrillic and Greek letters to Latin, leaving anything else as is.
+///
+/// The mapping follows the common romanisations, e.g. "ж" becomes "zh" and "θ" becomes
+/// "th", and is applied to already folded text.
+pub fn transliterate(text: &str) -> String {
+    let mut latin = String::with_capacity(text.len());
+    for c in text.chars() {
+        let replacement = match c {
+            // Cyrillic, including the Ukrainian letters
+            'а' => "a",
+            'б' => "b",
+            'в' => "v",
+            'г' => "g",
+            'ґ' => "g",
+            'д' => "d",
+            'е' => "e",
+            'є' => "ye",
+            'ж' => "zh",
+            'з' => "z",
+            'и' => "i",
+            'і' => "i",
+            'ї' => "yi",
+            'й' => "y",
+            'к' => "k",
+            'л' => "l",
+            'м' => "m",
+            'н' => "n",
+            'о' => "o",
+            'п' => "p",
+            'р' => "r",
+            'с' => "s",
+            'т' => "t",
+            'у' => "u",
+            'ф' => "f",
+            'х' => "kh",
+            'ц' => "ts",
+            'ч' => "ch",
+            'ш' => "sh",
+            'щ' => "shch",
+            'ъ' | 'ь' => "",
+            'ы' => "y",
+            'э' => "e",
+            'ю' => "yu",
+            'я' => "ya",
+            // Greek
+            'α' => "a",
+            'β' => "v",
+            'γ' => "g",
+            'δ' => "d",
+            'ε' => "e",
+            'ζ' => "z",
+            'η' => "i",
+            'θ' => "th",
+            'ι' => "i",
+            'κ' => "k",
+            'λ' => "l",
+            'μ' => "m",
+            'ν' => "n",
+            'ξ' => "x",
+            'ο' => "o",
+            'π' => "p",
+            'ρ' => "r",
+            'σ' => "s",
+            'τ' => "t",
+            'υ' => "y",
+            'φ' => "f",
+            'χ' => "ch",
+            'ψ' => "ps",
+            'ω' => "o",
+            c => {
+                latin.push(c);
+                continue;
+            }
+        };
+        latin.push_str(replacement);
+    }
+    latin
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ranks the candidates for the query, best first, leaving out those that don't match.
+    fn rank<'a>(matcher: &Matcher, candidates: &[&'a str], query: &str) -> Vec<&'a str> {
+        let mut matches: Vec<(i64, &str)> = candidates
+            .iter()
+            .filter_map(|candidate| Some((matcher.score(candidate, query)?, *candidate)))
+            .collect();
+        matches.sort_by(|a, b| b.0.cmp(&a.0));
+        matches
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+
+    #[test]
+    fn finds_names_with_typos() {
+        let matcher = Matcher::new(false);
+        let apps = ["Files", "Firefox", "Text Editor", "LibreOffice Writer"];
+
+        assert_eq!(rank(&matcher, &apps, "firefx").first(), Some(&"Firefox"));
+        assert_eq!(rank(&matcher, &apps, "fierfox"), vec!["Firefox"]);
+        assert_eq!(
+            rank(&matcher, &apps, "libreofice"),
+            vec!["LibreOffice Writer"]
+        );
+        assert_eq!(rank(&matcher, &apps, "edtior"), vec!["Text Editor"]);
+    }
+
+    #[test]
+    fn short_queries_tolerate_no_typos() {
+        let matcher = Matcher::new(false);
+        assert_eq!(matcher.score("Files", "fli"), None);
+        assert_eq!(matcher.score("Firefox", "xyz"), None);
+    }
+
+    #[test]
+    fn exact_prefix_outranks_typo() {
+        let matcher = Matcher::new(false);
+        let exact = matcher.score("Firefox Web Browser", "firefox").unwrap();
+        let typo = matcher.score("Firefix", "firefox").unwrap();
+        assert!(exact > typo, "{exact} should be above {typo}");
+
+        let apps = ["Firefix", "Firefox Web Browser"];
+        assert_eq!(
+            rank(&matcher, &apps, "firefox"),
+            vec!["Firefox Web Browser", "Firefix"]
+        );
+    }
+
+    #[test]
+    fn ignores_case_and_diacritics() {
+        let matcher = Matcher::new(false);
+        assert!(matcher.score("Café", "cafe").is_some());
+        assert!(matcher.score("cafe", "CAFÉ").is_some());
+        assert!(matcher.score("Kalkulačka", "kalkulacka").is_some());
+        assert!(matcher.score("Straße", "strasse").is_some());
+        assert!(matcher.score("Øresund", "oresund").is_some());
+    }
+
+    #[test]
+    fn transliterates_other_scripts() {
+        let matcher = Matcher::new(true);
+        assert!(matcher.score("Терминал", "terminal").is_some());
+        assert!(matcher.score("Θερμόμετρο", "thermometro").is_some());
+        assert!(matcher.score("Мой компьютер", "moy").is_some());
+        assert!(matcher.score("Terminal", "терминал").is_some());
+
+        let matcher = Matcher::new(false);
+        assert_eq!(matcher.score("Терминал", "terminal"), None);
+        assert!(matcher.score("Терминал", "терм").is_some());
+    }
+
+    #[test]
+    fn keeps_short_i_distinct() {
+        assert_eq!(fold("Й"), "й");
+        assert_eq!(fold("Ёлка"), "елка");
+        assert_eq!(transliterate("йогурт"), "yogurt");
+
+        let matcher = Matcher::new(false);
+        assert!(matcher.score("Мой компьютер", "мой").is_some());
+        assert_eq!(matcher.score("Мой компьютер", "мои"), None);
+    }
+
+    #[test]
+    fn folds_text() {
+        assert_eq!(fold("Ärger"), "arger");
+        assert_eq!(fold("ŒUVRE"), "oeuvre");
+        assert_eq!(fold("Ωμέγας"), "ωμεγασ");
+    }
+
+    #[test]
+    fn maps_expanded_characters_back() {
+        let (normalized, origins) = normalize("Straße", false);
+        assert_eq!(normalized, "strasse");
+        assert_eq!(origins, [0, 1, 2, 3, 4, 4, 5]);
+
+        let (normalized, origins) = normalize("Борщ", true);
+        assert_eq!(normalized, "borshch");
+        assert_eq!(origins, [0, 1, 2, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn highlights_original_characters() {
+        let matcher = Matcher::new(true);
+
+        // Both "s" of "ss" come from "ß", which is highlighted once
+        let (_, highlights) = matcher.score_with_indices("Straße", "strasse").unwrap();
+        assert_eq!(highlights, [0, 1, 2, 3, 4, 5]);
+        let (_, highlights) = matcher.score_with_indices("Fuß", "fuss").unwrap();
+        assert_eq!(highlights, [0, 1, 2]);
+
+        let (_, highlights) = matcher.score_with_indices("Борщ", "borshch").unwrap();
+        assert_eq!(highlights, [0, 1, 2, 3]);
+        let (_, highlights) = matcher.score_with_indices("Щука", "shchuka").unwrap();
+        assert_eq!(highlights, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn highlights_typo_matches() {
+        let matcher = Matcher::new(false);
+
+        let (_, highlights) = matcher.score_with_indices("Firefox", "fierfox").unwrap();
+        assert_eq!(highlights, [0, 1, 2, 3, 4, 5, 6]);
+        let (_, highlights) = matcher.score_with_indices("GNU Image", "imahe").unwrap();
+        assert_eq!(highlights, [4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(edit_distance("firefox", "firefox"), 0);
+        assert_eq!(edit_distance("firefox", "fierfox"), 1);
+        assert_eq!(edit_distance("firefox", "firefx"), 1);
+        assert_eq!(edit_distance("firefox", "firefoxy"), 1);
+        assert_eq!(edit_distance("abc", "xyz"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
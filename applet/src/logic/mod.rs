@@ -1 +1,14 @@
-pub mod apps;
\ No newline at end of file
+pub mod apps;
+pub mod calculator;
+pub mod commands;
+pub mod file_search;
+pub mod gnome_search;
+pub mod krunner;
+pub mod launcher;
+pub mod matching;
+pub mod query;
+pub mod search;
+pub mod search_history;
+pub mod settings_panels;
+pub mod system_tools;
+pub mod terminal;
@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Parses the scope prefixes of search queries.
+//!
+//! | Prefix            | Scope                                  |
+//! |-------------------|----------------------------------------|
+//! | `app:`            | applications only                      |
+//! | `f:`              | files and folders only                 |
+//! | `=`               | the calculator only                    |
+//! | `>`               | commands only                          |
+//! | `cat:<category>`  | applications of a category, e.g. `dev` |
+//!
+//! Prefixes are case-insensitive, `apps:`, `file:`, `files:` and `category:` work as
+//! well. Anything else, including unknown `key:` prefixes such as URLs, is searched as is.
+
+use crate::logic::search::SearchProviderKind;
+
+/// Prefix limiting the search to the calculator.
+pub const CALCULATOR_PREFIX: char = '=';
+
+/// Prefix limiting the search to commands.
+pub const COMMAND_PREFIX: char = '>';
+
+/// Limits a search to a single provider.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchScope {
+    Applications,
+    Files,
+    Calculator,
+    Commands,
+    /// Applications of a category, as typed after `cat:`
+    Category(String),
+}
+
+impl SearchScope {
+    /// The only provider queried within the scope.
+    pub fn provider(&self) -> SearchProviderKind {
+        match self {
+            SearchScope::Applications | SearchScope::Category(_) => {
+                SearchProviderKind::Applications
+            }
+            SearchScope::Files => SearchProviderKind::Files,
+            SearchScope::Calculator => SearchProviderKind::Calculator,
+            SearchScope::Commands => SearchProviderKind::Commands,
+        }
+    }
+}
+
+/// The content of the search field, split into its scope and the text to search for.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchQuery {
+    pub scope: Option<SearchScope>,
+    /// The query without the scope prefix, trimmed
+    pub text: String,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> SearchQuery {
+        let input = input.trim_start();
+
+        if let Some(text) = input.strip_prefix(CALCULATOR_PREFIX) {
+            return SearchQuery::scoped(SearchScope::Calculator, text);
+        }
+        if let Some(text) = input.strip_prefix(COMMAND_PREFIX) {
+            return SearchQuery::scoped(SearchScope::Commands, text);
+        }
+
+        // The key has to be the first word, so "open file: x" isn't scoped
+        let first_word = input.split_whitespace().next().unwrap_or_default();
+        if let Some((key, value)) = first_word.split_once(':') {
+            let rest = &input[key.len() + 1..];
+            match key.to_lowercase().as_str() {
+                "app" | "apps" => return SearchQuery::scoped(SearchScope::Applications, rest),
+                "f" | "file" | "files" => return SearchQuery::scoped(SearchScope::Files, rest),
+                "cat" | "category" if !value.is_empty() => {
+                    let text = &rest[value.len()..];
+                    return SearchQuery::scoped(SearchScope::Category(value.to_lowercase()), text);
+                }
+                _ => {}
+            }
+        }
+
+        SearchQuery {
+            scope: None,
+            text: input.trim().to_string(),
+        }
+    }
+
+    fn scoped(scope: SearchScope, text: &str) -> SearchQuery {
+        SearchQuery {
+            scope: Some(scope),
+            text: text.trim().to_string(),
+        }
+    }
+
+    /// Whether the query was explicitly scoped to the provider.
+    pub fn is_scoped_to(&self, provider: SearchProviderKind) -> bool {
+        self.scope
+            .as_ref()
+            .is_some_and(|scope| scope.provider() == provider)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoped(scope: SearchScope, text: &str) -> SearchQuery {
+        SearchQuery {
+            scope: Some(scope),
+            text: text.to_string(),
+        }
+    }
+
+    fn unscoped(text: &str) -> SearchQuery {
+        SearchQuery {
+            scope: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_applications_scope() {
+        assert_eq!(
+            SearchQuery::parse("app:fire"),
+            scoped(SearchScope::Applications, "fire")
+        );
+        assert_eq!(
+            SearchQuery::parse("apps: fire"),
+            scoped(SearchScope::Applications, "fire")
+        );
+        assert_eq!(
+            SearchQuery::parse("APP:Fire"),
+            scoped(SearchScope::Applications, "Fire")
+        );
+    }
+
+    #[test]
+    fn parses_files_scope() {
+        assert_eq!(
+            SearchQuery::parse("f:report"),
+            scoped(SearchScope::Files, "report")
+        );
+        assert_eq!(
+            SearchQuery::parse("file: report"),
+            scoped(SearchScope::Files, "report")
+        );
+        assert_eq!(
+            SearchQuery::parse("files:a b"),
+            scoped(SearchScope::Files, "a b")
+        );
+    }
+
+    #[test]
+    fn parses_calculator_scope() {
+        assert_eq!(
+            SearchQuery::parse("=2+2"),
+            scoped(SearchScope::Calculator, "2+2")
+        );
+        assert_eq!(
+            SearchQuery::parse("= 2 + 2 "),
+            scoped(SearchScope::Calculator, "2 + 2")
+        );
+    }
+
+    #[test]
+    fn parses_commands_scope() {
+        assert_eq!(
+            SearchQuery::parse(">ls -la"),
+            scoped(SearchScope::Commands, "ls -la")
+        );
+        assert_eq!(
+            SearchQuery::parse("> htop"),
+            scoped(SearchScope::Commands, "htop")
+        );
+    }
+
+    #[test]
+    fn parses_category_scope() {
+        assert_eq!(
+            SearchQuery::parse("cat:dev code"),
+            scoped(SearchScope::Category("dev".to_string()), "code")
+        );
+        assert_eq!(
+            SearchQuery::parse("category:Games"),
+            scoped(SearchScope::Category("games".to_string()), "")
+        );
+        // Without a category name it's ordinary text
+        assert_eq!(SearchQuery::parse("cat: dev"), unscoped("cat: dev"));
+    }
+
+    #[test]
+    fn keeps_unknown_prefixes() {
+        assert_eq!(
+            SearchQuery::parse("https://example.com"),
+            unscoped("https://example.com")
+        );
+        assert_eq!(SearchQuery::parse("foo:bar"), unscoped("foo:bar"));
+    }
+
+    #[test]
+    fn parses_prefix_without_text() {
+        assert_eq!(
+            SearchQuery::parse("app:"),
+            scoped(SearchScope::Applications, "")
+        );
+        assert_eq!(SearchQuery::parse("f:  "), scoped(SearchScope::Files, ""));
+        assert_eq!(SearchQuery::parse("="), scoped(SearchScope::Calculator, ""));
+        assert_eq!(SearchQuery::parse(">"), scoped(SearchScope::Commands, ""));
+    }
+
+    #[test]
+    fn ignores_leading_whitespace() {
+        assert_eq!(
+            SearchQuery::parse("  app:fire"),
+            scoped(SearchScope::Applications, "fire")
+        );
+        assert_eq!(
+            SearchQuery::parse("\t=1+1"),
+            scoped(SearchScope::Calculator, "1+1")
+        );
+        assert_eq!(SearchQuery::parse("  firefox  "), unscoped("firefox"));
+        assert_eq!(SearchQuery::parse("   "), unscoped(""));
+    }
+
+    #[test]
+    fn keeps_colons_in_text() {
+        assert_eq!(SearchQuery::parse("open file: x"), unscoped("open file: x"));
+        assert_eq!(SearchQuery::parse("10:30"), unscoped("10:30"));
+        assert_eq!(
+            SearchQuery::parse("app store: apps"),
+            unscoped("app store: apps")
+        );
+    }
+
+    #[test]
+    fn tells_the_scoped_provider() {
+        let query = SearchQuery::parse("cat:dev code");
+        assert!(query.is_scoped_to(SearchProviderKind::Applications));
+        assert!(!query.is_scoped_to(SearchProviderKind::Files));
+        assert!(!SearchQuery::parse("code").is_scoped_to(SearchProviderKind::Applications));
+    }
+}
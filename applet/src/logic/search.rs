@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cosmic::iced_futures::futures::future::{join_all, BoxFuture};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{RecentCommand, SearchHistoryEntry};
+use crate::fl;
+use crate::logic::apps::Apps;
+use crate::logic::calculator::Calculator;
+use crate::logic::commands::Commands;
+use crate::logic::file_search::FileSearch;
+use crate::logic::gnome_search::GnomeShellSearch;
+use crate::logic::krunner::KRunnerSearch;
+use crate::logic::query::SearchQuery;
+use crate::logic::settings_panels::{SettingsPanel, SettingsPanels};
+use crate::model::search_result::{SearchResult, SearchResultGroup};
+
+/// A source of search results.
+///
+/// Providers are queried concurrently for every change of the search field, and their
+/// results are shown grouped by provider in the order configured in the settings.
+pub trait SearchProvider: Send + Sync {
+    /// Looks up the results matching `query`, whose text is only empty when the query is
+    /// scoped to this provider.
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>>;
+}
+
+/// The settings and state providers need, snapshotted by the applet when it starts a
+/// search, so the config isn't read again for every keystroke.
+#[derive(Clone, Debug, Default)]
+pub struct SearchContext {
+    /// Commands run from the search field, most recent first
+    pub command_history: Vec<RecentCommand>,
+    /// Pages of COSMIC Settings, loaded along with the applications
+    pub settings_panels: Arc<Vec<SettingsPanel>>,
+    /// Folders whose files are searched, resolved by [`search_roots`]
+    ///
+    /// [`search_roots`]: crate::logic::file_search::search_roots
+    pub file_search_roots: Vec<PathBuf>,
+    /// Whether names in other scripts are matched by their Latin transliteration
+    pub transliterate: bool,
+    /// Queries whose results were activated, most recent first
+    pub search_history: Vec<SearchHistoryEntry>,
+}
+
+/// Identifies a search provider, used to enable and order providers in the config.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum SearchProviderKind {
+    Applications,
+    Calculator,
+    /// Commands typed into the search field
+    Commands,
+    /// Pages of COSMIC Settings
+    SettingsPanels,
+    /// Names of files and folders in the user's home
+    Files,
+    /// Applications implementing `org.gnome.Shell.SearchProvider2`
+    GnomeShell,
+    /// KRunner plugins implementing `org.kde.krunner1`
+    KRunner,
+}
+
+impl SearchProviderKind {
+    /// All providers, in their default order.
+    pub const ALL: [SearchProviderKind; 7] = [
+        SearchProviderKind::Calculator,
+        SearchProviderKind::Applications,
+        SearchProviderKind::SettingsPanels,
+        SearchProviderKind::Commands,
+        SearchProviderKind::Files,
+        SearchProviderKind::GnomeShell,
+        SearchProviderKind::KRunner,
+    ];
+
+    /// Providers enabled in a new config, optional backends have to be enabled manually.
+    pub const DEFAULT: [SearchProviderKind; 4] = [
+        SearchProviderKind::Calculator,
+        SearchProviderKind::Applications,
+        SearchProviderKind::SettingsPanels,
+        SearchProviderKind::Commands,
+    ];
+
+    pub fn display_name(&self) -> String {
+        match self {
+            SearchProviderKind::Applications => fl!("search-provider-applications"),
+            SearchProviderKind::Calculator => fl!("search-provider-calculator"),
+            SearchProviderKind::Commands => fl!("search-provider-commands"),
+            SearchProviderKind::SettingsPanels => fl!("search-provider-settings-panels"),
+            SearchProviderKind::Files => fl!("search-provider-files"),
+            SearchProviderKind::GnomeShell => fl!("search-provider-gnome-shell"),
+            SearchProviderKind::KRunner => fl!("search-provider-krunner"),
+        }
+    }
+
+    pub fn provider(&self, context: &SearchContext) -> Box<dyn SearchProvider> {
+        match self {
+            SearchProviderKind::Applications => Box::new(Apps {
+                transliterate: context.transliterate,
+                history: context.search_history.clone(),
+            }),
+            SearchProviderKind::Calculator => Box::new(Calculator),
+            SearchProviderKind::Commands => Box::new(Commands {
+                history: context.command_history.clone(),
+            }),
+            SearchProviderKind::SettingsPanels => Box::new(SettingsPanels {
+                panels: context.settings_panels.clone(),
+                transliterate: context.transliterate,
+            }),
+            SearchProviderKind::Files => Box::new(FileSearch {
+                roots: context.file_search_roots.clone(),
+                transliterate: context.transliterate,
+            }),
+            SearchProviderKind::GnomeShell => Box::new(GnomeShellSearch),
+            SearchProviderKind::KRunner => Box::new(KRunnerSearch),
+        }
+    }
+}
+
+/// Queries the given providers, returning their non-empty result groups in the same order.
+///
+/// A scoped query only queries the provider of its scope, even when it isn't enabled.
+/// Results naming a group of their own are split off into a separate group, which
+/// follows the other groups of the same provider.
+pub async fn search(
+    input: String,
+    providers: Vec<SearchProviderKind>,
+    context: SearchContext,
+) -> Vec<SearchResultGroup> {
+    let query = SearchQuery::parse(&input);
+    let providers = match &query.scope {
+        Some(scope) => vec![scope.provider()],
+        None if query.text.is_empty() => return vec![],
+        None => providers,
+    };
+
+    let queries = providers
+        .iter()
+        .map(|kind| kind.provider(&context).query(query.clone()));
+
+    let mut groups: Vec<SearchResultGroup> = vec![];
+    for (results, provider) in join_all(queries).await.into_iter().zip(providers) {
+        let first_group = groups.len();
+        for result in results {
+            let name = result
+                .group
+                .clone()
+                .unwrap_or_else(|| provider.display_name());
+            match groups[first_group..]
+                .iter_mut()
+                .find(|group| group.name == name)
+            {
+                Some(group) => group.results.push(result),
+                None => groups.push(SearchResultGroup {
+                    provider,
+                    name,
+                    results: vec![result],
+                }),
+            }
+        }
+    }
+
+    for group in &mut groups {
+        group.results.sort_by(|a, b| b.score.cmp(&a.score));
+    }
+
+    groups
+}
+
+/// Returns `XDG_DATA_HOME` followed by `XDG_DATA_DIRS`, in order of precedence.
+pub fn xdg_data_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| {
+            std::env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".local/share"))
+        });
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+
+    data_home
+        .into_iter()
+        .chain(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .collect()
+}
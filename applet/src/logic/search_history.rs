@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Remembers which queries led to which application, so that e.g. typing "ff" learns
+//! that the user is looking for Firefox.
+
+use crate::config::SearchHistoryEntry;
+use crate::logic::matching::fold;
+use crate::logic::query::SearchQuery;
+
+/// Number of entries kept in the history.
+pub const MAX_HISTORY: usize = 50;
+
+/// Number of recent queries suggested while the search field is empty.
+pub const MAX_RECENT_QUERIES: usize = 5;
+
+/// Boost of an application launched once for exactly the same query.
+const EXACT_QUERY_BOOST: i64 = 100;
+
+/// Boost of an application launched once for a query starting with the current one.
+const PREFIX_QUERY_BOOST: i64 = 40;
+
+/// Upper limit of the boost, so history can't bury much better matches forever.
+const MAX_BOOST: i64 = 500;
+
+/// Moves the query to the front of the history, counting the launch of `app_id`.
+pub fn record(history: &mut Vec<SearchHistoryEntry>, query: &str, app_id: Option<String>) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+
+    let launch_count = match history
+        .iter()
+        .position(|entry| entry.query == query && entry.app_id == app_id)
+    {
+        Some(index) => history.remove(index).launch_count.saturating_add(1),
+        None => 1,
+    };
+    history.insert(
+        0,
+        SearchHistoryEntry {
+            query: query.to_string(),
+            app_id,
+            launch_count,
+        },
+    );
+    history.truncate(MAX_HISTORY);
+}
+
+/// The most recent distinct queries.
+pub fn recent_queries(history: &[SearchHistoryEntry]) -> Vec<String> {
+    let mut queries: Vec<String> = vec![];
+    for entry in history {
+        if !queries.contains(&entry.query) {
+            queries.push(entry.query.clone());
+        }
+        if queries.len() == MAX_RECENT_QUERIES {
+            break;
+        }
+    }
+    queries
+}
+
+/// Ranking boost of an application, based on how often it was launched for the query or
+/// for longer queries starting with it.
+pub fn boost(history: &[SearchHistoryEntry], query: &str, app_id: &str) -> i64 {
+    let query = fold(query);
+    if query.is_empty() {
+        return 0;
+    }
+
+    let boost: i64 = history
+        .iter()
+        .filter(|entry| entry.app_id.as_deref() == Some(app_id))
+        .map(|entry| {
+            let previous = fold(&SearchQuery::parse(&entry.query).text);
+            let weight = if previous == query {
+                EXACT_QUERY_BOOST
+            } else if previous.starts_with(&query) {
+                PREFIX_QUERY_BOOST
+            } else {
+                0
+            };
+            weight * entry.launch_count as i64
+        })
+        .sum();
+
+    boost.min(MAX_BOOST)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(query: &str, app_id: &str, launch_count: u32) -> SearchHistoryEntry {
+        SearchHistoryEntry {
+            query: query.to_string(),
+            app_id: Some(app_id.to_string()),
+            launch_count,
+        }
+    }
+
+    #[test]
+    fn learns_abbreviations() {
+        let mut history = vec![];
+        record(&mut history, "ff", Some("org.mozilla.firefox".to_string()));
+
+        assert!(boost(&history, "ff", "org.mozilla.firefox") > 0);
+        assert_eq!(boost(&history, "ff", "org.gnome.Files"), 0);
+        // Typing the start of a learned query boosts too, but less
+        assert!(boost(&history, "f", "org.mozilla.firefox") > 0);
+        assert!(
+            boost(&history, "f", "org.mozilla.firefox")
+                < boost(&history, "ff", "org.mozilla.firefox")
+        );
+        assert_eq!(boost(&history, "fox", "org.mozilla.firefox"), 0);
+    }
+
+    #[test]
+    fn boosts_regardless_of_case_and_scope() {
+        let history = vec![entry("app:FF", "org.mozilla.firefox", 1)];
+
+        assert!(boost(&history, "ff", "org.mozilla.firefox") > 0);
+        assert_eq!(boost(&history, "", "org.mozilla.firefox"), 0);
+    }
+
+    #[test]
+    fn counts_repeated_launches() {
+        let mut history = vec![];
+        record(&mut history, "ff", Some("org.mozilla.firefox".to_string()));
+        record(&mut history, "term", None);
+        record(
+            &mut history,
+            " ff ",
+            Some("org.mozilla.firefox".to_string()),
+        );
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0], entry("ff", "org.mozilla.firefox", 2));
+        assert_eq!(history[1].query, "term");
+
+        record(&mut history, "   ", None);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn caps_boost() {
+        let history = vec![entry("ff", "org.mozilla.firefox", 1000)];
+
+        assert_eq!(boost(&history, "ff", "org.mozilla.firefox"), MAX_BOOST);
+    }
+
+    #[test]
+    fn caps_history() {
+        let mut history = vec![];
+        for index in 0..MAX_HISTORY + 10 {
+            record(&mut history, &format!("query {index}"), None);
+        }
+
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history[0].query, format!("query {}", MAX_HISTORY + 9));
+        assert_eq!(history[MAX_HISTORY - 1].query, "query 10");
+    }
+
+    #[test]
+    fn lists_recent_distinct_queries() {
+        let history: Vec<SearchHistoryEntry> = ["ff", "term", "ff", "a", "b", "c", "d"]
+            .into_iter()
+            .map(|query| entry(query, "app", 1))
+            .collect();
+
+        assert_eq!(recent_queries(&history), ["ff", "term", "a", "b", "c"]);
+        assert!(recent_queries(&[]).is_empty());
+    }
+}
@@ -0,0 +1,425 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Finds COSMIC Settings pages matching the search query.
+//!
+//! COSMIC Settings installs a hidden desktop entry per page, e.g.
+//! `com.system76.CosmicSettings.Wireless.desktop` running `cosmic-settings wireless`,
+//! whose localized names and keywords are searched. When those entries can't be found,
+//! e.g. inside the Flatpak sandbox, a bundled table of the common pages is used instead.
+//! The applet loads the pages with [`load_panels`] along with the applications, and
+//! passes them to the provider.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cosmic::iced_futures::futures::future::BoxFuture;
+use freedesktop_desktop_entry::DesktopEntry;
+
+use crate::applet::SystemTool;
+use crate::fl;
+use crate::logic::launcher::parse_exec;
+use crate::logic::matching::Matcher;
+use crate::logic::query::SearchQuery;
+use crate::logic::search::SearchProvider;
+use crate::model::search_result::{SearchAction, SearchIcon, SearchResult};
+
+/// Prefix of the desktop entries of the individual pages.
+const PAGE_ENTRY_PREFIX: &str = "com.system76.CosmicSettings.";
+
+/// A page of COSMIC Settings.
+#[derive(Clone, Debug)]
+pub struct SettingsPanel {
+    /// Argument passed to `cosmic-settings` to open the page
+    pub page: String,
+    pub name: String,
+    pub comment: Option<String>,
+    pub keywords: Vec<String>,
+    pub icon: String,
+}
+
+pub struct SettingsPanels {
+    pub panels: Arc<Vec<SettingsPanel>>,
+    pub transliterate: bool,
+}
+
+impl SearchProvider for SettingsPanels {
+    fn query(&self, query: SearchQuery) -> BoxFuture<'static, Vec<SearchResult>> {
+        let panels = self.panels.clone();
+        let matcher = Matcher::new(self.transliterate);
+        Box::pin(async move {
+            let query = query.text;
+
+            panels
+                .iter()
+                .cloned()
+                .filter_map(|panel| {
+                    // Matches of the name rank above matches of a keyword
+                    let (score, highlights, matched_field) =
+                        match matcher.score_with_indices(&panel.name, &query) {
+                            Some((score, highlights)) => (score * 2, highlights, None),
+                            None => {
+                                let (score, keyword) = panel
+                                    .keywords
+                                    .iter()
+                                    .filter_map(|keyword| {
+                                        Some((matcher.score(keyword, &query)?, keyword))
+                                    })
+                                    .max_by_key(|(score, _)| *score)?;
+                                let field = fl!("matched-keyword", keyword = keyword.as_str());
+                                (score, vec![], Some(field))
+                            }
+                        };
+
+                    Some(SearchResult {
+                        title: panel.name.clone(),
+                        subtitle: panel.comment,
+                        icon: SearchIcon::Named(panel.icon),
+                        score,
+                        group: None,
+                        highlights,
+                        matched_field,
+                        action: SearchAction::LaunchTool(SystemTool::SettingsPage {
+                            page: panel.page,
+                            name: panel.name,
+                        }),
+                    })
+                })
+                .collect()
+        })
+    }
+}
+
+/// Finds the pages on a blocking thread, falling back to the bundled ones.
+pub async fn load_panels() -> Arc<Vec<SettingsPanel>> {
+    let panels = tokio::task::spawn_blocking(installed_panels)
+        .await
+        .unwrap_or_default();
+    Arc::new(or_bundled(panels))
+}
+
+/// The pages found, or the bundled ones when there are none.
+fn or_bundled(panels: Vec<SettingsPanel>) -> Vec<SettingsPanel> {
+    if panels.is_empty() {
+        bundled_panels()
+    } else {
+        panels
+    }
+}
+
+/// Reads the desktop entries COSMIC Settings installs for its pages.
+pub fn installed_panels() -> Vec<SettingsPanel> {
+    panels_in(freedesktop_desktop_entry::default_paths())
+}
+
+/// Reads the page entries of the directories, the first entry of a page wins.
+fn panels_in(dirs: impl IntoIterator<Item = PathBuf>) -> Vec<SettingsPanel> {
+    let locale = std::env::var("LANG")
+        .ok()
+        .and_then(|l| l.split(".").next().map(str::to_string));
+    let mut panels: Vec<SettingsPanel> = vec![];
+
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+            let is_page_entry =
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        name.starts_with(PAGE_ENTRY_PREFIX) && name.ends_with(".desktop")
+                    });
+            if !is_page_entry {
+                continue;
+            }
+            let Ok(entry) = DesktopEntry::from_path(&path, Some(locale.as_slice())) else {
+                continue;
+            };
+
+            let args = parse_exec(entry.exec().unwrap_or_default());
+            let [program, page, ..] = args.as_slice() else {
+                continue;
+            };
+            if !program.ends_with("cosmic-settings") || panels.iter().any(|p| &p.page == page) {
+                continue;
+            }
+
+            panels.push(SettingsPanel {
+                page: page.clone(),
+                name: entry
+                    .name(locale.as_slice())
+                    .map(|name| name.into_owned())
+                    .unwrap_or_else(|| page.clone()),
+                comment: entry.comment(locale.as_slice()).map(|c| c.into_owned()),
+                keywords: entry
+                    .keywords(locale.as_slice())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|keyword| keyword.into_owned())
+                    .collect(),
+                icon: entry
+                    .icon()
+                    .unwrap_or("preferences-system-symbolic")
+                    .to_string(),
+            });
+        }
+    }
+
+    panels
+}
+
+/// The common pages, for when the desktop entries of COSMIC Settings aren't available.
+pub fn bundled_panels() -> Vec<SettingsPanel> {
+    let panel = |page: &str, name: String, keywords: String, icon: &str| SettingsPanel {
+        page: page.to_string(),
+        name,
+        comment: None,
+        keywords: keywords
+            .split(';')
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+            .map(str::to_string)
+            .collect(),
+        icon: icon.to_string(),
+    };
+
+    vec![
+        panel(
+            "wireless",
+            fl!("settings-page-wireless"),
+            fl!("settings-page-wireless-keywords"),
+            "network-wireless-symbolic",
+        ),
+        panel(
+            "wired",
+            fl!("settings-page-wired"),
+            fl!("settings-page-wired-keywords"),
+            "network-wired-symbolic",
+        ),
+        panel(
+            "vpn",
+            fl!("settings-page-vpn"),
+            fl!("settings-page-vpn-keywords"),
+            "network-vpn-symbolic",
+        ),
+        panel(
+            "bluetooth",
+            fl!("settings-page-bluetooth"),
+            fl!("settings-page-bluetooth-keywords"),
+            "bluetooth-active-symbolic",
+        ),
+        panel(
+            "displays",
+            fl!("settings-page-displays"),
+            fl!("settings-page-displays-keywords"),
+            "preferences-desktop-display-symbolic",
+        ),
+        panel(
+            "sound",
+            fl!("settings-page-sound"),
+            fl!("settings-page-sound-keywords"),
+            "preferences-sound-symbolic",
+        ),
+        panel(
+            "power",
+            fl!("settings-page-power"),
+            fl!("settings-page-power-keywords"),
+            "battery-symbolic",
+        ),
+        panel(
+            "appearance",
+            fl!("settings-page-appearance"),
+            fl!("settings-page-appearance-keywords"),
+            "preferences-desktop-appearance-symbolic",
+        ),
+        panel(
+            "wallpaper",
+            fl!("settings-page-wallpaper"),
+            fl!("settings-page-wallpaper-keywords"),
+            "preferences-desktop-wallpaper-symbolic",
+        ),
+        panel(
+            "keyboard",
+            fl!("settings-page-keyboard"),
+            fl!("settings-page-keyboard-keywords"),
+            "input-keyboard-symbolic",
+        ),
+        panel(
+            "mouse",
+            fl!("settings-page-mouse"),
+            fl!("settings-page-mouse-keywords"),
+            "input-mouse-symbolic",
+        ),
+        panel(
+            "touchpad",
+            fl!("settings-page-touchpad"),
+            fl!("settings-page-touchpad-keywords"),
+            "input-touchpad-symbolic",
+        ),
+        panel(
+            "date-time",
+            fl!("settings-page-date-time"),
+            fl!("settings-page-date-time-keywords"),
+            "preferences-system-time-symbolic",
+        ),
+        panel(
+            "region-language",
+            fl!("settings-page-region-language"),
+            fl!("settings-page-region-language-keywords"),
+            "preferences-desktop-locale-symbolic",
+        ),
+        panel(
+            "users",
+            fl!("settings-page-users"),
+            fl!("settings-page-users-keywords"),
+            "system-users-symbolic",
+        ),
+        panel(
+            "about",
+            fl!("settings-page-about"),
+            fl!("settings-page-about-keywords"),
+            "help-about-symbolic",
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory below the system's temporary directory, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str, files: &[(&str, String)]) -> Self {
+            let root = std::env::temp_dir().join(format!("{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            for (file, content) in files {
+                std::fs::write(root.join(file), content).unwrap();
+            }
+            TempDir(root)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn panel(page: &str, name: &str, keywords: &[&str]) -> SettingsPanel {
+        SettingsPanel {
+            page: page.to_string(),
+            name: name.to_string(),
+            comment: None,
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            icon: "preferences-system-symbolic".to_string(),
+        }
+    }
+
+    async fn search(panels: Vec<SettingsPanel>, query: &str) -> Vec<SearchResult> {
+        let provider = SettingsPanels {
+            panels: Arc::new(panels),
+            transliterate: false,
+        };
+        provider.query(SearchQuery::parse(query)).await
+    }
+
+    #[tokio::test]
+    async fn ranks_name_matches_above_keyword_matches() {
+        let results = search(
+            vec![
+                panel("sound", "Sound", &["Bluetooth"]),
+                panel("bluetooth", "Bluetooth", &[]),
+            ],
+            "bluetooth",
+        )
+        .await;
+
+        let [sound, bluetooth] = results.as_slice() else {
+            panic!("Unexpected results {results:?}");
+        };
+        assert_eq!(bluetooth.score, sound.score * 2);
+        assert!(!bluetooth.highlights.is_empty());
+        assert!(sound.highlights.is_empty());
+    }
+
+    #[tokio::test]
+    async fn labels_keyword_matches() {
+        let results = search(
+            vec![
+                panel("sound", "Sound", &["headset", "bluetooth"]),
+                panel("bluetooth", "Bluetooth", &[]),
+            ],
+            "blue",
+        )
+        .await;
+
+        assert_eq!(
+            results[0].matched_field,
+            Some(fl!("matched-keyword", keyword = "bluetooth"))
+        );
+        assert_eq!(results[1].matched_field, None);
+    }
+
+    #[test]
+    fn falls_back_to_bundled_panels() {
+        let bundled = or_bundled(vec![]);
+        assert_eq!(bundled.len(), bundled_panels().len());
+        assert!(bundled.iter().any(|panel| panel.page == "wireless"));
+
+        let found = or_bundled(vec![panel("sound", "Sound", &[])]);
+        let pages: Vec<&str> = found.iter().map(|panel| panel.page.as_str()).collect();
+        assert_eq!(pages, ["sound"]);
+    }
+
+    #[test]
+    fn reads_each_page_once() {
+        let entry = |name: &str, exec: &str| {
+            format!(
+                "[Desktop Entry]\nType=Application\nName={name}\nExec={exec}\n\
+                 Keywords=WLAN;Network;\nNoDisplay=true\n"
+            )
+        };
+        let user = TempDir::new(
+            "settings-panels-user",
+            &[(
+                "com.system76.CosmicSettings.Wireless.desktop",
+                entry("Wi-Fi", "cosmic-settings wireless"),
+            )],
+        );
+        let system = TempDir::new(
+            "settings-panels-system",
+            &[
+                (
+                    "com.system76.CosmicSettings.Wireless.desktop",
+                    entry("Wireless", "cosmic-settings wireless"),
+                ),
+                (
+                    "com.system76.CosmicSettings.Sound.desktop",
+                    entry("Sound", "/usr/bin/cosmic-settings sound"),
+                ),
+                (
+                    "com.system76.CosmicSettings.Other.desktop",
+                    entry("Other", "other-settings sound"),
+                ),
+                (
+                    "org.example.Sound.desktop",
+                    entry("Mixer", "cosmic-settings mixer"),
+                ),
+            ],
+        );
+
+        let mut panels = panels_in([user.0.clone(), system.0.clone()]);
+        panels.sort_by(|a, b| a.page.cmp(&b.page));
+
+        let pages: Vec<(&str, &str)> = panels
+            .iter()
+            .map(|panel| (panel.page.as_str(), panel.name.as_str()))
+            .collect();
+        assert_eq!(pages, [("sound", "Sound"), ("wireless", "Wi-Fi")]);
+        assert_eq!(panels[1].keywords, ["WLAN", "Network"]);
+    }
+}
@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::path::PathBuf;
+
+use freedesktop_desktop_entry::DesktopEntry;
+
+use crate::config::SystemToolEntry;
+use crate::logic::launcher::parse_exec;
+use crate::logic::terminal::is_installed;
+
+/// Finds the desktop file of an application ID in the XDG application directories.
+pub fn find_desktop_file(app_id: &str) -> Option<PathBuf> {
+    let file_name = format!("{}.desktop", app_id);
+    freedesktop_desktop_entry::default_paths()
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.exists())
+}
+
+/// Resolves the command line of a context menu target.
+///
+/// Targets naming a desktop entry use its `Exec` line, anything else is treated as a
+/// command. Returns `None` when the target isn't installed, or its desktop entry is
+/// deleted with `Hidden` or has a `TryExec` program that isn't installed. Entries with
+/// `NoDisplay` are kept, they are only left out of application menus.
+pub fn resolve_command(target: &str) -> Option<String> {
+    if let Some(path) = find_desktop_file(target) {
+        let entry = DesktopEntry::from_path(path, None::<&[&str]>).ok()?;
+        if entry.desktop_entry("Hidden") == Some("true")
+            || entry
+                .desktop_entry("TryExec")
+                .is_some_and(|program| !is_installed(program))
+        {
+            return None;
+        }
+        return entry.exec().map(str::to_string);
+    }
+
+    let program = parse_exec(target).into_iter().next()?;
+    is_installed(&program).then(|| target.to_string())
+}
+
+/// Returns the label and command line of every configured tool that is installed.
+///
+/// Looking for the tools reads the application directories, so this blocks.
+pub fn available_tools(tools: &[SystemToolEntry]) -> Vec<(String, String)> {
+    tools
+        .iter()
+        .filter_map(|tool| {
+            let command = resolve_command(&tool.target)?;
+            Some((tool.display_name(), command))
+        })
+        .collect()
+}
@@ -0,0 +1,128 @@
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::path::Path;
+
+/// A terminal emulator the applet knows how to run commands in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Terminal {
+    /// Name of the executable, also used as the value stored in the config.
+    pub exec: &'static str,
+    /// Name shown in the settings.
+    pub name: &'static str,
+    /// Arguments placed between the terminal and the command to run.
+    pub exec_args: &'static [&'static str],
+}
+
+/// Terminals in the order in which they are tried during auto-detection.
+///
+/// `xdg-terminal-exec` comes first, as it already honours the user's preferred terminal.
+pub const KNOWN_TERMINALS: &[Terminal] = &[
+    Terminal {
+        exec: "xdg-terminal-exec",
+        name: "xdg-terminal-exec",
+        exec_args: &[],
+    },
+    Terminal {
+        exec: "cosmic-term",
+        name: "COSMIC Terminal",
+        exec_args: &["-e"],
+    },
+    Terminal {
+        exec: "gnome-terminal",
+        name: "GNOME Terminal",
+        exec_args: &["--"],
+    },
+    Terminal {
+        exec: "ptyxis",
+        name: "Ptyxis",
+        exec_args: &["--"],
+    },
+    Terminal {
+        exec: "konsole",
+        name: "Konsole",
+        exec_args: &["-e"],
+    },
+    Terminal {
+        exec: "alacritty",
+        name: "Alacritty",
+        exec_args: &["-e"],
+    },
+    Terminal {
+        exec: "kitty",
+        name: "kitty",
+        exec_args: &[],
+    },
+    Terminal {
+        exec: "foot",
+        name: "Foot",
+        exec_args: &[],
+    },
+    Terminal {
+        exec: "wezterm",
+        name: "WezTerm",
+        exec_args: &["start", "--"],
+    },
+    Terminal {
+        exec: "xfce4-terminal",
+        name: "Xfce Terminal",
+        exec_args: &["-x"],
+    },
+    Terminal {
+        exec: "tilix",
+        name: "Tilix",
+        exec_args: &["-e"],
+    },
+    Terminal {
+        exec: "xterm",
+        name: "XTerm",
+        exec_args: &["-e"],
+    },
+];
+
+/// Returns the known terminals that are installed on the system.
+pub fn installed_terminals() -> Vec<&'static Terminal> {
+    KNOWN_TERMINALS
+        .iter()
+        .filter(|terminal| is_installed(terminal.exec))
+        .collect()
+}
+
+/// Returns the command prefix used to run a command in the preferred terminal.
+///
+/// An empty `preference` selects the first installed terminal, as does a preferred
+/// terminal that isn't installed.
+pub fn command_prefix(preference: &str) -> Option<Vec<String>> {
+    let preference = preference.trim();
+    if !preference.is_empty() {
+        match KNOWN_TERMINALS.iter().find(|t| t.exec == preference) {
+            Some(terminal) if is_installed(terminal.exec) => return Some(prefix_of(terminal)),
+            _ => log::warn!(
+                "Terminal '{}' isn't installed, using the first installed one",
+                preference
+            ),
+        }
+    }
+
+    installed_terminals()
+        .first()
+        .map(|terminal| prefix_of(terminal))
+}
+
+fn prefix_of(terminal: &Terminal) -> Vec<String> {
+    std::iter::once(terminal.exec)
+        .chain(terminal.exec_args.iter().copied())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Checks whether an executable is available, looking at the host system when sandboxed.
+pub fn is_installed(exec: &str) -> bool {
+    if which::which(exec).is_ok() {
+        return true;
+    }
+
+    std::env::var("FLATPAK_ID").is_ok()
+        && ["/run/host/usr/bin", "/run/host/usr/local/bin"]
+            .iter()
+            .any(|dir| Path::new(dir).join(exec).exists())
+}
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxies for: `org.freedesktop.login1`
+//!
+//! Declares the parts of the logind interfaces whose results the applet inspects
+//! directly. Power actions themselves go through [`logind_zbus::manager::ManagerProxy`].
+
+use zbus::proxy;
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub trait LogindManager {
+    /// CanPowerOff method
+    fn can_power_off(&self) -> zbus::Result<String>;
+
+    /// CanReboot method
+    fn can_reboot(&self) -> zbus::Result<String>;
+
+    /// CanSuspend method
+    fn can_suspend(&self) -> zbus::Result<String>;
+
+    /// CanHibernate method
+    fn can_hibernate(&self) -> zbus::Result<String>;
+
+    /// CanHybridSleep method
+    fn can_hybrid_sleep(&self) -> zbus::Result<String>;
+
+    /// CanSuspendThenHibernate method
+    fn can_suspend_then_hibernate(&self) -> zbus::Result<String>;
+
+    /// CanRebootToFirmwareSetup method
+    fn can_reboot_to_firmware_setup(&self) -> zbus::Result<String>;
+
+    /// SetRebootToFirmwareSetup method
+    fn set_reboot_to_firmware_setup(&self, enable: bool) -> zbus::Result<()>;
+
+    /// CanRebootToBootLoaderEntry method
+    fn can_reboot_to_boot_loader_entry(&self) -> zbus::Result<String>;
+
+    /// SetRebootToBootLoaderEntry method
+    fn set_reboot_to_boot_loader_entry(&self, boot_loader_entry: &str) -> zbus::Result<()>;
+
+    /// ScheduleShutdown method, `type_` is e.g. `poweroff` or `reboot`
+    fn schedule_shutdown(&self, type_: &str, usec: u64) -> zbus::Result<()>;
+
+    /// CancelScheduledShutdown method
+    fn cancel_scheduled_shutdown(&self) -> zbus::Result<bool>;
+
+    /// GetSessionByPID method
+    fn get_session_by_pid(&self, pid: u32) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    /// TerminateSession method
+    fn terminate_session(&self, session_id: &str) -> zbus::Result<()>;
+
+    /// ListSessions method, returning `(id, uid, user, seat, path)` tuples
+    #[allow(clippy::type_complexity)]
+    fn list_sessions(
+        &self,
+    ) -> zbus::Result<Vec<(String, u32, String, String, zbus::zvariant::OwnedObjectPath)>>;
+
+    /// BootLoaderEntries property
+    #[zbus(property)]
+    fn boot_loader_entries(&self) -> zbus::Result<Vec<String>>;
+
+    /// BlockInhibited property, the operations blocked by inhibitors separated by colons
+    #[zbus(property)]
+    fn block_inhibited(&self) -> zbus::Result<String>;
+
+    /// PrepareForSleep signal, `start` is false once the system resumed
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+
+    /// ScheduledShutdown property, the shutdown type and its time in microseconds since
+    /// the epoch, or an empty type when nothing is scheduled
+    #[zbus(property)]
+    fn scheduled_shutdown(&self) -> zbus::Result<(String, u64)>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1"
+)]
+pub trait LogindSession {
+    /// Id property
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    /// Class property
+    #[zbus(property)]
+    fn class(&self) -> zbus::Result<String>;
+
+    /// VTNr property
+    #[zbus(property, name = "VTNr")]
+    fn vtnr(&self) -> zbus::Result<u32>;
+
+    /// Seat property, the seat ID and its object path
+    #[zbus(property)]
+    fn seat(&self) -> zbus::Result<(String, zbus::zvariant::OwnedObjectPath)>;
+
+    /// Timestamp property, the start of the session in microseconds since the epoch
+    #[zbus(property)]
+    fn timestamp(&self) -> zbus::Result<u64>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Seat",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/seat/auto"
+)]
+pub trait LogindSeat {
+    /// SwitchTo method
+    fn switch_to(&self, vtnr: u32) -> zbus::Result<()>;
+
+    /// Id property
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+}
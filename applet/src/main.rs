@@ -9,9 +9,18 @@ mod logic;
 mod power_options;
 mod cosmic_session;
 mod session_manager;
+mod kde_shutdown;
+mod gnome_search_provider;
+mod krunner_runner;
+mod logind_manager;
+mod display_manager;
+mod systemd_manager;
+mod notifications;
 mod applet_button;
 mod applet_menu;
 mod model;
+#[cfg(test)]
+mod test_bus;
 
 fn main() -> cosmic::iced::Result {
     // Initialize logging
@@ -11,6 +11,10 @@ pub struct ApplicationEntry {
     pub id: String,
     pub icon: Option<IconHandle>,
     pub comment: Option<String>,
+    /// Generic name, e.g. "Web Browser"
+    pub generic_name: Option<String>,
+    /// Additional words the application can be found by
+    pub keywords: Vec<String>,
     pub exec: Option<String>,
     pub category: Vec<String>,
     pub is_terminal: bool,
@@ -24,9 +28,33 @@ pub enum IconHandle {
 
 impl Into<ApplicationEntry> for DesktopEntryData {
     fn into(self) -> ApplicationEntry {
+        let locale = std::env::var("LANG")
+            .ok()
+            .and_then(|l| l.split(".").next().map(str::to_string));
+        let desktop_entry = self
+            .path
+            .as_ref()
+            .and_then(|path| DesktopEntry::from_path(path, Some(locale.as_slice())).ok());
+
         ApplicationEntry {
-            comment: get_comment(&self),
-            is_terminal: get_is_terminal(&self),
+            comment: desktop_entry.as_ref().map(|entry| {
+                entry
+                    .comment(locale.as_slice())
+                    .unwrap_or_default()
+                    .into_owned()
+            }),
+            generic_name: desktop_entry
+                .as_ref()
+                .and_then(|entry| entry.generic_name(locale.as_slice()))
+                .map(|name| name.into_owned()),
+            keywords: desktop_entry
+                .as_ref()
+                .and_then(|entry| entry.keywords(locale.as_slice()))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|keyword| keyword.into_owned())
+                .collect(),
+            is_terminal: desktop_entry.as_ref().is_some_and(|entry| entry.terminal()),
             id: self.id,
             name: self.name,
             icon: {
@@ -65,38 +93,3 @@ impl Default for IconHandle {
         )
     }
 }
-
-fn get_comment(app: &DesktopEntryData) -> Option<String> {
-    if let Some(path) = &app.path {
-        let locale = std::env::var("LANG")
-            .ok()
-            .and_then(|l| l.split(".").next().map(str::to_string));
-        let desktop_entry = DesktopEntry::from_path(path, Some(locale.as_slice()));
-
-        if let Ok(entry) = desktop_entry {
-            return Some(
-                entry
-                    .comment(locale.as_slice())
-                    .unwrap_or_default()
-                    .into_owned(),
-            );
-        }
-    }
-
-    None
-}
-
-fn get_is_terminal(app: &DesktopEntryData) -> bool {
-    if let Some(path) = &app.path {
-        let locale = std::env::var("LANG")
-            .ok()
-            .and_then(|l| l.split(".").next().map(str::to_string));
-        let desktop_entry = DesktopEntry::from_path(path, Some(locale.as_slice()));
-
-        if let Ok(entry) = desktop_entry {
-            return entry.terminal();
-        }
-    }
-
-    false
-}
@@ -1 +1,2 @@
-pub mod application_entry;
\ No newline at end of file
+pub mod application_entry;
+pub mod search_result;
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::applet::SystemTool;
+use crate::config::RecentCommand;
+use crate::logic::gnome_search::GnomeShellResult;
+use crate::logic::krunner::KRunnerResult;
+use crate::logic::search::SearchProviderKind;
+use crate::model::application_entry::{ApplicationEntry, IconHandle};
+
+#[derive(Clone, Debug)]
+/// A single result shown in the app list while searching.
+pub struct SearchResult {
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub icon: SearchIcon,
+    /// Relevance of the result within its provider, higher is better
+    pub score: i64,
+    /// Title of the group the result is shown in, defaults to the provider's name
+    pub group: Option<String>,
+    /// Indices of the characters of the title that matched the query in ascending order,
+    /// shown emphasised
+    pub highlights: Vec<usize>,
+    /// Describes what matched when the title didn't, shown in place of the subtitle
+    pub matched_field: Option<String>,
+    /// What happens when the result is activated
+    pub action: SearchAction,
+}
+
+#[derive(Clone, Debug)]
+pub enum SearchIcon {
+    /// An icon from the icon theme
+    Named(String),
+    /// An already resolved icon, e.g. of an application
+    Handle(IconHandle),
+}
+
+#[derive(Clone, Debug)]
+pub enum SearchAction {
+    LaunchApplication(Arc<ApplicationEntry>),
+    CopyToClipboard(String),
+    ActivateGnomeShellResult(GnomeShellResult),
+    RunKRunnerMatch(KRunnerResult),
+    RunCommand(RecentCommand),
+    LaunchTool(SystemTool),
+    /// Opens a file or folder with its default application
+    OpenPath(PathBuf),
+}
+
+#[derive(Clone, Debug)]
+/// Results shown together under a common title, usually those of one search provider.
+pub struct SearchResultGroup {
+    pub provider: SearchProviderKind,
+    pub name: String,
+    pub results: Vec<SearchResult>,
+}
@@ -0,0 +1,30 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxy for: `org.freedesktop.Notifications`
+//!
+//! Only the subset of the notification interface used by the applet is declared here.
+
+use std::collections::HashMap;
+
+use zbus::proxy;
+use zbus::zvariant::Value;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    /// Notify method
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
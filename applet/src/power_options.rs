@@ -1,4 +1,5 @@
 use zbus::Connection;
+use accounts_zbus::{AccountsProxy, UserProxy as AccountsUserProxy};
 use rustix::process::getuid;
 use logind_zbus::{
     manager::ManagerProxy,
@@ -6,7 +7,444 @@ use logind_zbus::{
     user::UserProxy,
 };
 
-use crate::{cosmic_session::CosmicSessionProxy, session_manager::SessionManagerProxy};
+use std::collections::HashMap;
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone};
+use cosmic::{
+    iced::{stream, Subscription},
+    iced_futures::futures::{self, future, SinkExt, Stream, StreamExt},
+};
+
+use crate::{
+    applet::PowerAction,
+    cosmic_session::CosmicSessionProxy,
+    display_manager::{DisplayManagerSeatProxy, GdmLocalDisplayFactoryProxy},
+    kde_shutdown::KdeShutdownProxy,
+    logind_manager::{LogindManagerProxy, LogindSeatProxy, LogindSessionProxy},
+    session_manager::SessionManagerProxy,
+};
+
+/// Availability of a power action, as reported by logind's `Can*` methods.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Capability {
+    /// The action can be performed right away
+    Yes,
+    /// The action is possible, but requires authentication
+    Challenge,
+    /// The action is forbidden by policy
+    No,
+    /// The hardware or system doesn't support the action
+    NotAvailable,
+}
+
+impl From<&str> for Capability {
+    fn from(value: &str) -> Self {
+        match value {
+            "yes" => Capability::Yes,
+            "challenge" => Capability::Challenge,
+            "no" => Capability::No,
+            _ => Capability::NotAvailable,
+        }
+    }
+}
+
+/// Queries logind for the availability of every power action it handles.
+///
+/// A method failing, e.g. `CanSuspendThenHibernate` on an older logind, only marks its
+/// own action as not available.
+///
+/// The results change over time, [`capabilities_watcher`] tells when to query again.
+pub async fn capabilities() -> zbus::Result<HashMap<PowerAction, Capability>> {
+    let connection = Connection::system().await?;
+    query_capabilities(&connection).await
+}
+
+async fn query_capabilities(
+    connection: &Connection,
+) -> zbus::Result<HashMap<PowerAction, Capability>> {
+    let manager = LogindManagerProxy::new(connection).await?;
+
+    let (power_off, reboot, suspend, hibernate, hybrid_sleep, suspend_then_hibernate) = tokio::join!(
+        manager.can_power_off(),
+        manager.can_reboot(),
+        manager.can_suspend(),
+        manager.can_hibernate(),
+        manager.can_hybrid_sleep(),
+        manager.can_suspend_then_hibernate()
+    );
+
+    let switch_user = match can_switch_user(connection).await {
+        Ok(true) => Capability::Yes,
+        Ok(false) => Capability::NotAvailable,
+        Err(e) => {
+            log::warn!("Failed to check whether the user can be switched: {}", e);
+            Capability::NotAvailable
+        }
+    };
+
+    Ok(HashMap::from([
+        capability(PowerAction::Shutdown, power_off),
+        capability(PowerAction::Reboot, reboot),
+        capability(PowerAction::Suspend, suspend),
+        capability(PowerAction::Hibernate, hibernate),
+        capability(PowerAction::HybridSleep, hybrid_sleep),
+        capability(PowerAction::SuspendThenHibernate, suspend_then_hibernate),
+        (PowerAction::SwitchUser, switch_user),
+    ]))
+}
+
+/// Reads the result of a `Can*` method, treating a failed call as not available.
+fn capability(action: PowerAction, result: zbus::Result<String>) -> (PowerAction, Capability) {
+    match result {
+        Ok(value) => (action, Capability::from(value.as_str())),
+        Err(e) => {
+            log::warn!("Failed to check whether {:?} is available: {}", action, e);
+            (action, Capability::NotAvailable)
+        }
+    }
+}
+
+/// Emits whenever the results of logind's `Can*` methods may have changed.
+///
+/// logind doesn't signal changes of the results themselves. They depend on the block
+/// inhibitors though, which logind announces through `BlockInhibited`, and on hardware
+/// such as swap space for hibernation, which may be different after a resume. Changes
+/// of polkit policy aren't announced at all, so the applet also queries again while the
+/// menu is open.
+pub fn capabilities_watcher() -> Subscription<()> {
+    struct CapabilitiesWatch;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<CapabilitiesWatch>(),
+        stream::channel(4, move |mut output| async move {
+            let result: zbus::Result<()> = async {
+                let connection = Connection::system().await?;
+                let mut changes = capability_changes(&connection).await?;
+                while changes.next().await.is_some() {
+                    _ = output.send(()).await;
+                }
+
+                Ok(())
+            }
+            .await;
+
+            if let Err(e) = result {
+                log::warn!("Failed to watch the power capabilities: {}", e);
+            }
+
+            future::pending().await
+        }),
+    )
+}
+
+/// Merges the changes of logind's inhibitors with the resumes from sleep.
+async fn capability_changes(
+    connection: &Connection,
+) -> zbus::Result<impl Stream<Item = ()> + Unpin + 'static> {
+    let manager = LogindManagerProxy::new(connection).await?;
+    // The first value is the current one
+    let inhibitor_changes = manager
+        .receive_block_inhibited_changed()
+        .await
+        .skip(1)
+        .map(|_| ());
+    let resumes = manager
+        .receive_prepare_for_sleep()
+        .await?
+        .filter(|signal| future::ready(signal.args().is_ok_and(|args| !*args.start())))
+        .map(|_| ());
+
+    Ok(futures::stream::select(inhibitor_changes, resumes))
+}
+
+/// Well-known name of the cosmic-greeter daemon on the system bus.
+const COSMIC_GREETER: &str = "com.system76.CosmicGreeter";
+
+/// Whether another user could log in through a login screen brought up next to the session.
+async fn can_switch_user(connection: &Connection) -> zbus::Result<bool> {
+    if !has_other_users(connection).await? {
+        return Ok(false);
+    }
+
+    let dbus = zbus::fdo::DBusProxy::new(connection).await?;
+    if dbus
+        .name_has_owner("org.gnome.DisplayManager".try_into()?)
+        .await?
+    {
+        return Ok(true);
+    }
+    if std::env::var("XDG_SEAT_PATH").is_ok()
+        && dbus
+            .name_has_owner("org.freedesktop.DisplayManager".try_into()?)
+            .await?
+    {
+        return Ok(true);
+    }
+    if has_cosmic_greeter(connection).await? {
+        return Ok(true);
+    }
+
+    Ok(greeter_vt(connection).await?.is_some())
+}
+
+/// Checks whether accounts other than the current user's can log in.
+///
+/// AccountsService only lists regular accounts, system accounts as defined by
+/// `login.defs` are left out already.
+async fn has_other_users(connection: &Connection) -> zbus::Result<bool> {
+    let accounts = AccountsProxy::new(connection).await?;
+    let our_uid = u64::from(getuid().as_raw());
+
+    for user_path in accounts.list_cached_users().await? {
+        let user = AccountsUserProxy::builder(connection)
+            .path(user_path)?
+            .build()
+            .await?;
+        if user.uid().await? != our_uid && !user.locked().await? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Whether cosmic-greeter runs or can be started, its lock screen lets other users log in.
+async fn has_cosmic_greeter(connection: &Connection) -> zbus::Result<bool> {
+    let dbus = zbus::fdo::DBusProxy::new(connection).await?;
+    if dbus.name_has_owner(COSMIC_GREETER.try_into()?).await? {
+        return Ok(true);
+    }
+
+    Ok(dbus
+        .list_activatable_names()
+        .await?
+        .iter()
+        .any(|name| name.as_str() == COSMIC_GREETER))
+}
+
+/// An inhibitor lock preventing a power action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inhibitor {
+    /// Name of the application holding the lock
+    pub who: String,
+    /// Reason given by the application
+    pub why: String,
+}
+
+/// Lists the applications blocking a power action.
+///
+/// Only `block` mode inhibitors are returned, `delay` ones just postpone the action
+/// for a moment and are held by many system services at all times.
+pub async fn inhibitors(action: PowerAction) -> zbus::Result<Vec<Inhibitor>> {
+    let inhibited_operation = match action {
+        PowerAction::Shutdown | PowerAction::Reboot => "shutdown",
+        PowerAction::Suspend
+        | PowerAction::Hibernate
+        | PowerAction::HybridSleep
+        | PowerAction::SuspendThenHibernate => "sleep",
+        PowerAction::Logout | PowerAction::Lock | PowerAction::SwitchUser => return Ok(vec![]),
+    };
+
+    let connection = Connection::system().await?;
+    let manager = ManagerProxy::new(&connection).await?;
+
+    // `(what, who, why, mode, uid, pid)` tuples, `what` lists the inhibited operations
+    // separated by colons
+    let inhibitors: Vec<(String, String, String, String, u32, u32)> =
+        manager.inner().call("ListInhibitors", &()).await?;
+
+    Ok(inhibitors
+        .into_iter()
+        .filter(|(what, _, _, mode, _, _)| {
+            mode == "block" && what.split(':').any(|w| w == inhibited_operation)
+        })
+        .map(|(_, who, why, _, _, _)| Inhibitor { who, why })
+        .collect())
+}
+
+/// Where the system should boot into after an advanced restart.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RestartTarget {
+    FirmwareSetup,
+    BootLoaderEntry(String),
+}
+
+/// Lists the targets available for an advanced restart.
+///
+/// The firmware setup and the boot loader entries are checked independently, so one of
+/// them failing doesn't hide the other.
+pub async fn restart_targets() -> zbus::Result<Vec<RestartTarget>> {
+    let connection = Connection::system().await?;
+    let manager = LogindManagerProxy::new(&connection).await?;
+    let is_allowed = |capability: zbus::Result<String>| {
+        capability.map(|capability| {
+            matches!(
+                Capability::from(capability.as_str()),
+                Capability::Yes | Capability::Challenge
+            )
+        })
+    };
+
+    let mut targets = vec![];
+    match is_allowed(manager.can_reboot_to_firmware_setup().await) {
+        Ok(true) => targets.push(RestartTarget::FirmwareSetup),
+        Ok(false) => {}
+        Err(e) => log::warn!("Failed to check for firmware setup support: {}", e),
+    }
+
+    let entries = async {
+        if is_allowed(manager.can_reboot_to_boot_loader_entry().await)? {
+            manager.boot_loader_entries().await
+        } else {
+            Ok(vec![])
+        }
+    }
+    .await;
+    match entries {
+        Ok(entries) => targets.extend(entries.into_iter().map(RestartTarget::BootLoaderEntry)),
+        Err(e) => log::warn!("Failed to list boot loader entries: {}", e),
+    }
+
+    Ok(targets)
+}
+
+/// Restarts the system into the given target.
+///
+/// When the restart fails, e.g. because it was inhibited or not authorized, the target
+/// is reset again, so the next ordinary restart doesn't end up in it.
+pub async fn restart_into(target: RestartTarget) -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager = LogindManagerProxy::new(&connection).await?;
+    set_restart_target(&manager, &target, true).await?;
+
+    if let Err(e) = restart().await {
+        if let Err(reset_error) = set_restart_target(&manager, &target, false).await {
+            log::error!("Failed to reset the restart target: {}", reset_error);
+        }
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+async fn set_restart_target(
+    manager: &LogindManagerProxy<'_>,
+    target: &RestartTarget,
+    enable: bool,
+) -> zbus::Result<()> {
+    match target {
+        RestartTarget::FirmwareSetup => manager.set_reboot_to_firmware_setup(enable).await,
+        RestartTarget::BootLoaderEntry(entry) => {
+            // An empty entry removes the one set before
+            let entry = if enable { entry.as_str() } else { "" };
+            manager.set_reboot_to_boot_loader_entry(entry).await
+        }
+    }
+}
+
+/// A shutdown or restart scheduled with logind.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScheduledShutdown {
+    /// Either [`PowerAction::Shutdown`] or [`PowerAction::Reboot`]
+    pub action: PowerAction,
+    pub at: DateTime<Local>,
+}
+
+impl ScheduledShutdown {
+    /// Reads logind's `ScheduledShutdown` property, `None` means nothing is scheduled.
+    fn from_property((kind, usec): (String, u64)) -> Option<Self> {
+        let action = match kind.as_str() {
+            "poweroff" | "dry-poweroff" | "halt" | "dry-halt" => PowerAction::Shutdown,
+            "reboot" | "dry-reboot" | "kexec" | "dry-kexec" => PowerAction::Reboot,
+            _ => return None,
+        };
+        let at = Local.timestamp_micros(i64::try_from(usec).ok()?).single()?;
+
+        Some(ScheduledShutdown { action, at })
+    }
+}
+
+/// Schedules a shutdown or restart at the given time.
+pub async fn schedule_shutdown(action: PowerAction, at: DateTime<Local>) -> zbus::Result<()> {
+    let kind = match action {
+        PowerAction::Shutdown => "poweroff",
+        PowerAction::Reboot => "reboot",
+        _ => {
+            return Err(zbus::Error::Failure(format!(
+                "{:?} can't be scheduled",
+                action
+            )))
+        }
+    };
+
+    let connection = Connection::system().await?;
+    let manager = LogindManagerProxy::new(&connection).await?;
+    manager
+        .schedule_shutdown(kind, at.timestamp_micros().max(0) as u64)
+        .await
+}
+
+/// Cancels the scheduled shutdown, if any.
+///
+/// logind answers `false` when nothing was scheduled, for example because the shutdown was
+/// cancelled elsewhere already, which leaves the same state behind as a cancellation.
+pub async fn cancel_scheduled_shutdown() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager = LogindManagerProxy::new(&connection).await?;
+    manager.cancel_scheduled_shutdown().await?;
+
+    Ok(())
+}
+
+/// Returns the next moment the wall clock shows `time`, today or tomorrow.
+pub fn next_occurrence(time: NaiveTime) -> Option<DateTime<Local>> {
+    let now = Local::now();
+    let today = now.date_naive().and_time(time);
+    let at = if today > now.naive_local() {
+        today
+    } else {
+        today + chrono::Duration::days(1)
+    };
+
+    at.and_local_timezone(Local).earliest()
+}
+
+/// Emits the scheduled shutdown on startup and whenever logind reports a change.
+pub fn scheduled_shutdown_subscription() -> Subscription<Option<ScheduledShutdown>> {
+    struct ScheduledShutdownWatch;
+
+    Subscription::run_with_id(
+        std::any::TypeId::of::<ScheduledShutdownWatch>(),
+        stream::channel(4, move |mut output| async move {
+            let result: zbus::Result<()> = async {
+                let connection = Connection::system().await?;
+                let manager = LogindManagerProxy::new(&connection).await?;
+                let mut changes = manager.receive_scheduled_shutdown_changed().await;
+
+                let scheduled = manager.scheduled_shutdown().await?;
+                _ = output
+                    .send(ScheduledShutdown::from_property(scheduled))
+                    .await;
+
+                while let Some(change) = changes.next().await {
+                    let scheduled = change.get().await?;
+                    _ = output
+                        .send(ScheduledShutdown::from_property(scheduled))
+                        .await;
+                }
+
+                Ok(())
+            }
+            .await;
+
+            if let Err(e) = result {
+                log::warn!("Failed to watch the scheduled shutdown: {}", e);
+            }
+
+            cosmic::iced_futures::futures::future::pending().await
+        }),
+    )
+}
 
 pub async fn restart() -> zbus::Result<()> {
     let connection = Connection::system().await?;
@@ -26,6 +464,24 @@ pub async fn suspend() -> zbus::Result<()> {
     manager_proxy.suspend(true).await
 }
 
+pub async fn hibernate() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager_proxy = ManagerProxy::new(&connection).await?;
+    manager_proxy.hibernate(true).await
+}
+
+pub async fn hybrid_sleep() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager_proxy = ManagerProxy::new(&connection).await?;
+    manager_proxy.hybrid_sleep(true).await
+}
+
+pub async fn suspend_then_hibernate() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager_proxy = ManagerProxy::new(&connection).await?;
+    manager_proxy.suspend_then_hibernate(true).await
+}
+
 pub async fn lock() -> zbus::Result<()> {
     let connection = Connection::system().await?;
     let manager_proxy = ManagerProxy::new(&connection).await?;
@@ -63,20 +519,456 @@ pub async fn lock() -> zbus::Result<()> {
     }
 }
 
+/// Locks the current session and brings up the login screen, so another user can log in.
+///
+/// GDM is asked for a new transient display first, then display managers implementing
+/// `org.freedesktop.DisplayManager` (e.g. LightDM) are asked to switch to their greeter.
+/// Greeters which keep their own logind session are reached by switching the seat to the
+/// greeter's virtual terminal. greetd ends its greeter session after login though, so
+/// with cosmic-greeter the locked session is left to its lock screen, where another user
+/// can log in.
+pub async fn switch_user() -> zbus::Result<()> {
+    let lock_result = lock().await;
+    if let Err(e) = &lock_result {
+        log::warn!("Failed to lock the session before switching user: {}", e);
+    }
+
+    let connection = Connection::system().await?;
+
+    let gdm_result = async {
+        GdmLocalDisplayFactoryProxy::new(&connection)
+            .await?
+            .create_transient_display()
+            .await
+    }
+    .await;
+    if gdm_result.is_ok() {
+        return Ok(());
+    }
+
+    if let Ok(seat_path) = std::env::var("XDG_SEAT_PATH") {
+        let seat_result = async {
+            DisplayManagerSeatProxy::builder(&connection)
+                .path(seat_path)?
+                .build()
+                .await?
+                .switch_to_greeter()
+                .await
+        }
+        .await;
+        if seat_result.is_ok() {
+            return Ok(());
+        }
+    }
+
+    match switch_to_greeter_session(&connection).await {
+        Err(_) if has_cosmic_greeter(&connection).await.unwrap_or(false) => lock_result,
+        result => result,
+    }
+}
+
+/// Switches the current seat to the virtual terminal of a running greeter session.
+async fn switch_to_greeter_session(connection: &Connection) -> zbus::Result<()> {
+    match greeter_vt(connection).await? {
+        Some(vtnr) => {
+            LogindSeatProxy::new(connection)
+                .await?
+                .switch_to(vtnr)
+                .await
+        }
+        None => Err(zbus::Error::Failure(
+            "no display manager greeter is available".to_string(),
+        )),
+    }
+}
+
+/// Finds the virtual terminal of a greeter session on the current seat.
+async fn greeter_vt(connection: &Connection) -> zbus::Result<Option<u32>> {
+    let manager = LogindManagerProxy::new(connection).await?;
+    let seat_id = LogindSeatProxy::new(connection).await?.id().await?;
+
+    for (_, _, _, session_seat, session_path) in manager.list_sessions().await? {
+        if session_seat != seat_id {
+            continue;
+        }
+
+        let session = LogindSessionProxy::builder(connection)
+            .path(session_path)?
+            .build()
+            .await?;
+        if session.class().await? != "greeter" {
+            continue;
+        }
+
+        let vtnr = session.vtnr().await?;
+        if vtnr > 0 {
+            return Ok(Some(vtnr));
+        }
+    }
+
+    Ok(None)
+}
+
+/// A session manager able to end the desktop session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SessionManager {
+    Cosmic,
+    Gnome,
+    Kde,
+}
+
+impl SessionManager {
+    const ALL: [SessionManager; 3] = [
+        SessionManager::Cosmic,
+        SessionManager::Gnome,
+        SessionManager::Kde,
+    ];
+
+    /// Well-known name the session manager owns on the session bus.
+    fn bus_name(&self) -> &'static str {
+        match self {
+            SessionManager::Cosmic => "com.system76.CosmicSession",
+            SessionManager::Gnome => "org.gnome.SessionManager",
+            SessionManager::Kde => "org.kde.Shutdown",
+        }
+    }
+
+    /// Whether the session manager belongs to a desktop listed in `XDG_CURRENT_DESKTOP`.
+    fn matches_desktop(&self, desktop: &str) -> bool {
+        let desktop = desktop.to_ascii_lowercase();
+        match self {
+            SessionManager::Cosmic => desktop == "cosmic",
+            // Pop!_OS used to report its GNOME session as `pop:GNOME`
+            SessionManager::Gnome => matches!(
+                desktop.as_str(),
+                "gnome" | "gnome-classic" | "ubuntu" | "pop" | "unity"
+            ),
+            SessionManager::Kde => desktop == "kde",
+        }
+    }
+
+    async fn log_out(&self, connection: &Connection) -> zbus::Result<()> {
+        match self {
+            SessionManager::Cosmic => CosmicSessionProxy::new(connection).await?.exit().await,
+            SessionManager::Gnome => SessionManagerProxy::new(connection).await?.logout(0).await,
+            SessionManager::Kde => KdeShutdownProxy::new(connection).await?.logout().await,
+        }
+    }
+}
+
+/// Lists the session managers running on the session bus, those of the current desktop first.
+async fn running_session_managers(connection: &Connection) -> zbus::Result<Vec<SessionManager>> {
+    let desktops = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+    let mut candidates: Vec<SessionManager> = desktops
+        .split(':')
+        .flat_map(|desktop| {
+            SessionManager::ALL
+                .into_iter()
+                .filter(move |manager| manager.matches_desktop(desktop.trim()))
+        })
+        .collect();
+    for manager in SessionManager::ALL {
+        if !candidates.contains(&manager) {
+            candidates.push(manager);
+        }
+    }
+
+    let dbus = zbus::fdo::DBusProxy::new(connection).await?;
+    let mut running = vec![];
+    for manager in candidates {
+        let name = zbus::names::BusName::try_from(manager.bus_name())?;
+        if dbus.name_has_owner(name).await.unwrap_or(false) {
+            running.push(manager);
+        }
+    }
+
+    Ok(running)
+}
+
+/// Ends the desktop session through its session manager.
+///
+/// The session managers present on the bus are tried in turn, preferring the one of the
+/// desktop named in `XDG_CURRENT_DESKTOP`. When none of them succeeds, the logind session
+/// the applet runs in is terminated instead.
 pub async fn log_out() -> zbus::Result<()> {
-    // TODO: Fix imports
-    let session_type = std::env::var("XDG_CURRENT_DESKTOP").ok();
     let connection = Connection::session().await?;
-    match session_type.as_ref().map(|s| s.trim()) {
-        Some("pop:GNOME") => {
-            let manager_proxy = SessionManagerProxy::new(&connection).await?;
-            manager_proxy.logout(0).await?;
+    for manager in running_session_managers(&connection).await? {
+        match manager.log_out(&connection).await {
+            Ok(()) => return Ok(()),
+            Err(e) => log::warn!("Failed to log out through {:?}: {}", manager, e),
         }
-        // By default assume COSMIC
+    }
+
+    terminate_session().await.map_err(|e| {
+        zbus::Error::Failure(format!("no session manager could end the session: {}", e))
+    })
+}
+
+/// Terminates the logind session of the applet.
+async fn terminate_session() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager = LogindManagerProxy::new(&connection).await?;
+
+    let session_id = match std::env::var("XDG_SESSION_ID") {
+        Ok(session_id) if !session_id.is_empty() => session_id,
         _ => {
-            let cosmic_session = CosmicSessionProxy::new(&connection).await?;
-            cosmic_session.exit().await?;
+            let session_path = manager.get_session_by_pid(std::process::id()).await?;
+            LogindSessionProxy::builder(&connection)
+                .path(session_path)?
+                .build()
+                .await?
+                .id()
+                .await?
+        }
+    };
+
+    manager.terminate_session(&session_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_bus;
+    use std::time::Duration;
+    use zbus::{fdo, interface, object_server::SignalEmitter, zvariant::OwnedObjectPath};
+
+    /// Answers logind's `Can*` methods, failing those without an answer like an older
+    /// logind lacking the method would.
+    struct FakeLogind {
+        answers: HashMap<&'static str, &'static str>,
+        block_inhibited: String,
+    }
+
+    impl FakeLogind {
+        fn answer(&self, method: &str) -> fdo::Result<String> {
+            self.answers
+                .get(method)
+                .map(|answer| answer.to_string())
+                .ok_or_else(|| fdo::Error::UnknownMethod(method.to_string()))
         }
     }
-    Ok(())
-}
\ No newline at end of file
+
+    #[interface(name = "org.freedesktop.login1.Manager")]
+    impl FakeLogind {
+        fn can_power_off(&self) -> fdo::Result<String> {
+            self.answer("CanPowerOff")
+        }
+
+        fn can_reboot(&self) -> fdo::Result<String> {
+            self.answer("CanReboot")
+        }
+
+        fn can_suspend(&self) -> fdo::Result<String> {
+            self.answer("CanSuspend")
+        }
+
+        fn can_hibernate(&self) -> fdo::Result<String> {
+            self.answer("CanHibernate")
+        }
+
+        fn can_hybrid_sleep(&self) -> fdo::Result<String> {
+            self.answer("CanHybridSleep")
+        }
+
+        fn can_suspend_then_hibernate(&self) -> fdo::Result<String> {
+            self.answer("CanSuspendThenHibernate")
+        }
+
+        #[zbus(property)]
+        fn block_inhibited(&self) -> String {
+            self.block_inhibited.clone()
+        }
+
+        #[zbus(signal)]
+        async fn prepare_for_sleep(emitter: &SignalEmitter<'_>, start: bool) -> zbus::Result<()>;
+    }
+
+    /// Lists the accounts served next to it, like AccountsService.
+    struct FakeAccounts {
+        users: Vec<OwnedObjectPath>,
+    }
+
+    #[interface(name = "org.freedesktop.Accounts")]
+    impl FakeAccounts {
+        fn list_cached_users(&self) -> Vec<OwnedObjectPath> {
+            self.users.clone()
+        }
+    }
+
+    struct FakeUser {
+        uid: u64,
+        locked: bool,
+    }
+
+    #[interface(name = "org.freedesktop.Accounts.User")]
+    impl FakeUser {
+        #[zbus(property)]
+        fn uid(&self) -> u64 {
+            self.uid
+        }
+
+        #[zbus(property)]
+        fn locked(&self) -> bool {
+            self.locked
+        }
+    }
+
+    /// Serves the accounts given as UID and whether the account is locked.
+    async fn serve_accounts(service: &Connection, accounts: &[(u64, bool)]) {
+        let mut users = Vec::new();
+        for &(uid, locked) in accounts {
+            let path = OwnedObjectPath::try_from(format!("/org/freedesktop/Accounts/User{uid}"))
+                .expect("Invalid user path");
+            service
+                .object_server()
+                .at(&path, FakeUser { uid, locked })
+                .await
+                .expect("Failed to serve the fake user");
+            users.push(path);
+        }
+
+        service
+            .object_server()
+            .at("/org/freedesktop/Accounts", FakeAccounts { users })
+            .await
+            .expect("Failed to serve the fake accounts");
+    }
+
+    async fn has_other_users_among(accounts: &[(u64, bool)]) -> bool {
+        let logind = FakeLogind {
+            answers: HashMap::new(),
+            block_inhibited: String::new(),
+        };
+        let (connection, service) = test_bus::serve("/org/freedesktop/login1", logind).await;
+        serve_accounts(&service, accounts).await;
+
+        has_other_users(&connection)
+            .await
+            .expect("Failed to list the fake accounts")
+    }
+
+    async fn capabilities_of(
+        answers: &[(&'static str, &'static str)],
+    ) -> HashMap<PowerAction, Capability> {
+        let logind = FakeLogind {
+            answers: answers.iter().copied().collect(),
+            block_inhibited: String::new(),
+        };
+        let (connection, service) = test_bus::serve("/org/freedesktop/login1", logind).await;
+        serve_accounts(&service, &[(u64::from(getuid().as_raw()), false)]).await;
+
+        query_capabilities(&connection)
+            .await
+            .expect("Failed to query the fake logind")
+    }
+
+    #[tokio::test]
+    async fn finds_other_users() {
+        let our_uid = u64::from(getuid().as_raw());
+
+        assert!(!has_other_users_among(&[(our_uid, false)]).await);
+        assert!(!has_other_users_among(&[(our_uid, false), (our_uid + 1, true)]).await);
+        assert!(has_other_users_among(&[(our_uid, false), (our_uid + 1, false)]).await);
+    }
+
+    #[test]
+    fn parses_capabilities() {
+        assert_eq!(Capability::from("yes"), Capability::Yes);
+        assert_eq!(Capability::from("challenge"), Capability::Challenge);
+        assert_eq!(Capability::from("no"), Capability::No);
+        assert_eq!(Capability::from("na"), Capability::NotAvailable);
+        assert_eq!(Capability::from(""), Capability::NotAvailable);
+    }
+
+    #[tokio::test]
+    async fn reads_every_capability() {
+        let capabilities = capabilities_of(&[
+            ("CanPowerOff", "yes"),
+            ("CanReboot", "challenge"),
+            ("CanSuspend", "no"),
+            ("CanHibernate", "na"),
+            ("CanHybridSleep", "yes"),
+            ("CanSuspendThenHibernate", "challenge"),
+        ])
+        .await;
+
+        assert_eq!(capabilities[&PowerAction::Shutdown], Capability::Yes);
+        assert_eq!(capabilities[&PowerAction::Reboot], Capability::Challenge);
+        assert_eq!(capabilities[&PowerAction::Suspend], Capability::No);
+        assert_eq!(
+            capabilities[&PowerAction::Hibernate],
+            Capability::NotAvailable
+        );
+        assert_eq!(capabilities[&PowerAction::HybridSleep], Capability::Yes);
+        assert_eq!(
+            capabilities[&PowerAction::SuspendThenHibernate],
+            Capability::Challenge
+        );
+        assert_eq!(
+            capabilities[&PowerAction::SwitchUser],
+            Capability::NotAvailable
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_method_only_hides_its_action() {
+        let capabilities = capabilities_of(&[
+            ("CanPowerOff", "yes"),
+            ("CanReboot", "yes"),
+            ("CanSuspend", "yes"),
+            ("CanHibernate", "yes"),
+            ("CanHybridSleep", "yes"),
+        ])
+        .await;
+
+        assert_eq!(
+            capabilities[&PowerAction::SuspendThenHibernate],
+            Capability::NotAvailable
+        );
+        assert_eq!(capabilities[&PowerAction::Shutdown], Capability::Yes);
+        assert_eq!(capabilities[&PowerAction::Hibernate], Capability::Yes);
+    }
+
+    #[tokio::test]
+    async fn announces_inhibitor_changes_and_resumes() {
+        let logind = FakeLogind {
+            answers: HashMap::new(),
+            block_inhibited: String::new(),
+        };
+        let (connection, service) = test_bus::serve("/org/freedesktop/login1", logind).await;
+        let mut changes = capability_changes(&connection)
+            .await
+            .expect("Failed to watch the fake logind");
+        let logind = service
+            .object_server()
+            .interface::<_, FakeLogind>("/org/freedesktop/login1")
+            .await
+            .expect("The fake logind isn't served");
+        let timeout = Duration::from_secs(1);
+
+        // Only the resume is a change, going to sleep isn't
+        FakeLogind::prepare_for_sleep(logind.signal_emitter(), true)
+            .await
+            .expect("Failed to emit PrepareForSleep");
+        FakeLogind::prepare_for_sleep(logind.signal_emitter(), false)
+            .await
+            .expect("Failed to emit PrepareForSleep");
+        assert_eq!(
+            tokio::time::timeout(timeout, changes.next()).await,
+            Ok(Some(()))
+        );
+        assert!(tokio::time::timeout(timeout, changes.next()).await.is_err());
+
+        logind.get_mut().await.block_inhibited = "shutdown:sleep".to_string();
+        logind
+            .get()
+            .await
+            .block_inhibited_changed(logind.signal_emitter())
+            .await
+            .expect("Failed to announce BlockInhibited");
+        assert_eq!(
+            tokio::time::timeout(timeout, changes.next()).await,
+            Ok(Some(()))
+        );
+    }
+}
@@ -0,0 +1,23 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! # DBus interface proxy for: `org.freedesktop.systemd1.Manager`
+//!
+//! Only the subset of the systemd manager interface used by the applet is declared here.
+
+use zbus::proxy;
+use zbus::zvariant::{OwnedObjectPath, Value};
+
+#[proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+pub trait SystemdManager {
+    /// StartTransientUnit method
+    fn start_transient_unit(
+        &self,
+        name: &str,
+        mode: &str,
+        properties: &[(&str, Value<'_>)],
+        aux: &[(&str, &[(&str, Value<'_>)])],
+    ) -> zbus::Result<OwnedObjectPath>;
+}
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: GPL-3.0-only
+//! Private buses for tests, serving fake D-Bus services to the code under test.
+
+use zbus::{connection, object_server::Interface, Connection, Guid};
+
+/// Serves the interface at `path` on a private peer-to-peer bus.
+///
+/// Returns the connection of the code under test and the one of the fake service, which
+/// has to be kept alive for as long as the service is used.
+pub async fn serve<I: Interface>(path: &'static str, interface: I) -> (Connection, Connection) {
+    let (client, server) = tokio::net::UnixStream::pair().expect("Failed to create a socket pair");
+
+    tokio::try_join!(
+        connection::Builder::unix_stream(client).p2p().build(),
+        async {
+            connection::Builder::unix_stream(server)
+                .server(Guid::generate())?
+                .p2p()
+                .serve_at(path, interface)?
+                .build()
+                .await
+        },
+    )
+    .expect("Failed to set up the private bus")
+}
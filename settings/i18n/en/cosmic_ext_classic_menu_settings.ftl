@@ -18,6 +18,37 @@ select-custom-icon = Select a custom icon
 select = Select
 icon-file = Icon file (*.svg, *.png)
 
+launching = Launching
+launch-in-scope = Launch applications in their own systemd scope
+terminal = Terminal for command-line applications
+
+context-menu = Context menu
+system-tools = System tools
+customize = Customize
+search = Search
+search-providers = Search providers
+transliterate-search = Match Cyrillic and Greek names when typing in Latin
+search-history = Search history
+clear-search-history = Clear
+system-tool-label = Label
+system-tool-target = Application ID or command
+add = Add
+
+power = Power
+builtin-power-confirmation = Always use the menu's own confirmation
+scheduled-shutdown-countdown = Show a countdown on the panel button while a shutdown is scheduled
+confirm-shutdown = Ask before shutting down
+confirm-restart = Ask before restarting
+confirm-log-out = Ask before logging out
+confirm-suspend = Ask before suspending
+confirm-lock-screen = Ask before locking the screen
+confirm-hibernate = Ask before hibernating
+confirm-hybrid-sleep = Ask before entering hybrid sleep
+confirm-suspend-then-hibernate = Ask before suspending, then hibernating
+confirm-switch-user = Ask before switching user
+
+power-buttons = Power buttons
+
 top = Top
 bottom = Bottom
 left = Left
@@ -28,4 +59,6 @@ icon-and-label = Icon and Label
 auto = Auto
 username-prefered = Username prefered
 realname-prefered = Realname prefered
-none = None
\ No newline at end of file
+none = None
+username-with-session-info = Username and session details
+realname-with-session-info = Realname and session details
\ No newline at end of file
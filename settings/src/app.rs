@@ -9,9 +9,11 @@ use cosmic::prelude::*;
 use cosmic::widget::{button, icon, menu, menu::{ItemWidth, ItemHeight}};
 use cosmic::{iced::Background, widget::text, Element};
 use cosmic_ext_classic_menu_applet::config::{
-    AppletButtonStyle, AppletConfig, HorizontalPosition, UserWidgetStyle,
-    VerticalPosition,
+    AppletButtonStyle, AppletConfig, AppletState, HorizontalPosition, SystemToolEntry,
+    UserWidgetStyle, VerticalPosition,
 };
+use cosmic_ext_classic_menu_applet::logic::search::SearchProviderKind;
+use cosmic_ext_classic_menu_applet::logic::terminal::{self, Terminal};
 use futures_util::SinkExt;
 use std::collections::HashMap;
 use std::fs;
@@ -30,6 +32,12 @@ pub struct AppModel {
     key_binds: HashMap<menu::KeyBind, MenuAction>,
     // Configuration data that persists between application runs.
     config: AppletConfig,
+    /// Terminal emulators found on the system.
+    terminals: Vec<&'static Terminal>,
+    /// Label of the context menu item being added.
+    new_tool_label: String,
+    /// Target of the context menu item being added.
+    new_tool_target: String,
 }
 
 /// Messages emitted by the application and its widgets.
@@ -47,6 +55,23 @@ pub enum Message {
     OpenIconPicker,
     ButtonIconChanged(PathBuf),
     CustomIconSelected,
+    LaunchInScopeToggled(bool),
+    TerminalChanged(usize),
+    NewSystemToolLabelChanged(String),
+    NewSystemToolTargetChanged(String),
+    SystemToolAdded,
+    SystemToolRemoved(usize),
+    SystemToolMovedUp(usize),
+    SystemToolMovedDown(usize),
+    BuiltinPowerConfirmationToggled(bool),
+    PowerConfirmationToggled(PowerAction, bool),
+    PowerActionToggled(PowerAction, bool),
+    ScheduledShutdownCountdownToggled(bool),
+    TransliterateSearchToggled(bool),
+    ClearSearchHistory,
+    SearchProviderToggled(SearchProviderKind, bool),
+    SearchProviderMovedUp(usize),
+    SearchProviderMovedDown(usize),
 }
 
 /// Create a COSMIC application from the app model
@@ -103,8 +128,18 @@ impl cosmic::Application for AppModel {
             key_binds: HashMap::new(),
             // Optional configuration file for an application.
             config: AppletConfig::config(),
+            terminals: terminal::installed_terminals(),
+            new_tool_label: String::new(),
+            new_tool_target: String::new(),
         };
 
+        if app.selected_terminal_position().is_none() {
+            log::warn!(
+                "Terminal '{}' isn't installed, the applet picks one on its own",
+                app.config.terminal
+            );
+        }
+
         (app, Task::none())
     }
 
@@ -185,7 +220,9 @@ impl cosmic::Application for AppModel {
                 vec![
                     fl!("username-prefered"),
                     fl!("realname-prefered"),
-                    fl!("none")
+                    fl!("none"),
+                    fl!("username-with-session-info"),
+                    fl!("realname-with-session-info")
                 ],
                 Some(self.config.user_widget as usize),
                 Message::UserWidgetChanged
@@ -201,37 +238,144 @@ impl cosmic::Application for AppModel {
             cosmic::widget::button::text(fl!("button-icon-placeholder"))
                 .on_press(Message::OpenIconPicker) // 4. Open picker on click
         ];
+        let launch_in_scope = cosmic::iced::widget::row![
+            cosmic::widget::Space::new(Length::Fill, 5),
+            cosmic::widget::toggler(self.config.launch_in_scope)
+                .on_toggle(Message::LaunchInScopeToggled)
+        ];
+        let terminal = cosmic::iced::widget::row![
+            cosmic::widget::Space::new(Length::Fill, 5),
+            cosmic::widget::dropdown(
+                std::iter::once(fl!("auto"))
+                    .chain(self.terminals.iter().map(|t| t.name.to_string()))
+                    .collect::<Vec<_>>(),
+                self.selected_terminal(),
+                Message::TerminalChanged
+            )
+        ];
+        let system_tools = cosmic::iced::widget::row![
+            cosmic::widget::Space::new(Length::Fill, 5),
+            cosmic::widget::button::text(fl!("customize"))
+                .on_press(Message::ToggleContextPage(ContextPage::SystemTools))
+        ];
+        let search_providers = cosmic::iced::widget::row![
+            cosmic::widget::Space::new(Length::Fill, 5),
+            cosmic::widget::button::text(fl!("customize"))
+                .on_press(Message::ToggleContextPage(ContextPage::SearchProviders))
+        ];
+
+        let general_section = cosmic::widget::settings::section()
+            .title(fl!("general"))
+            .add(cosmic::widget::settings::item(
+                fl!("app-menu-position"),
+                app_menu_position,
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("search-field-position"),
+                search_field_position,
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("applet-button-style"),
+                applet_button_style,
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("user-widget"),
+                user_widget,
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("button-label"),
+                button_label,
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("button-icon"),
+                button_icon,
+            ));
+
+        let launching_section = cosmic::widget::settings::section()
+            .title(fl!("launching"))
+            .add(cosmic::widget::settings::item(
+                fl!("launch-in-scope"),
+                launch_in_scope,
+            ))
+            .add(cosmic::widget::settings::item(fl!("terminal"), terminal));
+
+        let context_menu_section = cosmic::widget::settings::section()
+            .title(fl!("context-menu"))
+            .add(cosmic::widget::settings::item(
+                fl!("system-tools"),
+                system_tools,
+            ));
 
-        let settings_container =
-            cosmic::widget::settings::view_column(vec![cosmic::widget::settings::section()
-                .title(fl!("general"))
-                .add(cosmic::widget::settings::item(
-                    fl!("app-menu-position"),
-                    app_menu_position,
-                ))
-                .add(cosmic::widget::settings::item(
-                    fl!("search-field-position"),
-                    search_field_position,
-                ))
-                .add(cosmic::widget::settings::item(
-                    fl!("applet-button-style"),
-                    applet_button_style,
-                ))
-                .add(cosmic::widget::settings::item(
-                    fl!("user-widget"),
-                    user_widget,
-                ))
-                .add(cosmic::widget::settings::item(
-                    fl!("button-label"),
-                    button_label,
-                ))
-                .add(cosmic::widget::settings::item(
-                    fl!("button-icon"),
-                    button_icon,
-                ))
-                .into()]);
-
-        settings_container.padding([5, 10]).into()
+        let search_section = cosmic::widget::settings::section()
+            .title(fl!("search"))
+            .add(cosmic::widget::settings::item(
+                fl!("search-providers"),
+                search_providers,
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("transliterate-search"),
+                cosmic::widget::toggler(self.config.transliterate_search)
+                    .on_toggle(Message::TransliterateSearchToggled),
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("search-history"),
+                cosmic::widget::button::standard(fl!("clear-search-history"))
+                    .on_press(Message::ClearSearchHistory),
+            ));
+
+        let mut power_section = cosmic::widget::settings::section()
+            .title(fl!("power"))
+            .add(cosmic::widget::settings::item(
+                fl!("builtin-power-confirmation"),
+                cosmic::widget::toggler(self.config.builtin_power_confirmation)
+                    .on_toggle(Message::BuiltinPowerConfirmationToggled),
+            ))
+            .add(cosmic::widget::settings::item(
+                fl!("scheduled-shutdown-countdown"),
+                cosmic::widget::toggler(self.config.show_scheduled_shutdown_countdown)
+                    .on_toggle(Message::ScheduledShutdownCountdownToggled),
+            ));
+        for (action, label) in [
+            (PowerAction::Shutdown, fl!("confirm-shutdown")),
+            (PowerAction::Reboot, fl!("confirm-restart")),
+            (PowerAction::Logout, fl!("confirm-log-out")),
+            (PowerAction::Suspend, fl!("confirm-suspend")),
+            (PowerAction::Lock, fl!("confirm-lock-screen")),
+            (PowerAction::Hibernate, fl!("confirm-hibernate")),
+            (PowerAction::HybridSleep, fl!("confirm-hybrid-sleep")),
+            (
+                PowerAction::SuspendThenHibernate,
+                fl!("confirm-suspend-then-hibernate"),
+            ),
+            (PowerAction::SwitchUser, fl!("confirm-switch-user")),
+        ] {
+            power_section = power_section.add(cosmic::widget::settings::item(
+                label,
+                cosmic::widget::toggler(self.config.confirm_power_actions.contains(&action))
+                    .on_toggle(move |enabled| Message::PowerConfirmationToggled(action, enabled)),
+            ));
+        }
+
+        let mut power_buttons_section =
+            cosmic::widget::settings::section().title(fl!("power-buttons"));
+        for action in PowerAction::ALL {
+            power_buttons_section = power_buttons_section.add(cosmic::widget::settings::item(
+                action.display_name(),
+                cosmic::widget::toggler(self.config.enabled_power_actions.contains(&action))
+                    .on_toggle(move |enabled| Message::PowerActionToggled(action, enabled)),
+            ));
+        }
+
+        let settings_container = cosmic::widget::settings::view_column(vec![
+            general_section.into(),
+            launching_section.into(),
+            context_menu_section.into(),
+            search_section.into(),
+            power_section.into(),
+            power_buttons_section.into(),
+        ]);
+
+        cosmic::widget::scrollable(settings_container.padding([5, 10])).into()
     }
 
     /// Display a context drawer if the context page is requested.
@@ -252,6 +396,16 @@ impl cosmic::Application for AppModel {
                 Message::ToggleContextPage(ContextPage::IconPicker),
             )
             .title(fl!("button-icon")),
+            ContextPage::SystemTools => context_drawer::context_drawer(
+                self.system_tools_editor(),
+                Message::ToggleContextPage(ContextPage::SystemTools),
+            )
+            .title(fl!("system-tools")),
+            ContextPage::SearchProviders => context_drawer::context_drawer(
+                self.search_providers_editor(),
+                Message::ToggleContextPage(ContextPage::SearchProviders),
+            )
+            .title(fl!("search-providers")),
         })
     }
 
@@ -356,6 +510,8 @@ impl cosmic::Application for AppModel {
                     0 => UserWidgetStyle::UsernamePrefered,
                     1 => UserWidgetStyle::RealNamePrefered,
                     2 => UserWidgetStyle::None,
+                    3 => UserWidgetStyle::UsernameWithSessionInfo,
+                    4 => UserWidgetStyle::RealNameWithSessionInfo,
                     _ => UserWidgetStyle::None,
                 };
 
@@ -394,6 +550,206 @@ impl cosmic::Application for AppModel {
 
                 Task::none()
             }
+            Message::LaunchInScopeToggled(launch_in_scope) => {
+                log::info!("Launch in scope changed to: {:?}", launch_in_scope);
+                self.config.launch_in_scope = launch_in_scope;
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write launch in scope config");
+
+                Task::none()
+            }
+            Message::TerminalChanged(index) => {
+                // The first option lets the applet pick a terminal on its own
+                self.config.terminal = match index {
+                    0 => String::new(),
+                    i => self
+                        .terminals
+                        .get(i - 1)
+                        .map(|t| t.exec.to_string())
+                        .unwrap_or_default(),
+                };
+                log::info!("Terminal changed to: {:?}", self.config.terminal);
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write terminal config");
+
+                Task::none()
+            }
+            Message::NewSystemToolLabelChanged(label) => {
+                self.new_tool_label = label;
+
+                Task::none()
+            }
+            Message::NewSystemToolTargetChanged(target) => {
+                self.new_tool_target = target;
+
+                Task::none()
+            }
+            Message::SystemToolAdded => {
+                let target = self.new_tool_target.trim().to_string();
+                if target.is_empty() {
+                    return Task::none();
+                }
+
+                log::info!("System tool added: {:?}", target);
+                let label = self.new_tool_label.trim();
+                self.config.system_tools.push(SystemToolEntry {
+                    label: (!label.is_empty()).then(|| label.to_string()),
+                    target,
+                });
+                self.new_tool_label.clear();
+                self.new_tool_target.clear();
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write system tools config");
+
+                Task::none()
+            }
+            Message::SystemToolRemoved(index) => {
+                if index < self.config.system_tools.len() {
+                    let removed = self.config.system_tools.remove(index);
+                    log::info!("System tool removed: {:?}", removed.target);
+
+                    self.config
+                        .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                        .expect("Failed to write system tools config");
+                }
+
+                Task::none()
+            }
+            Message::SystemToolMovedUp(index) => {
+                if index > 0 && index < self.config.system_tools.len() {
+                    self.config.system_tools.swap(index, index - 1);
+
+                    self.config
+                        .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                        .expect("Failed to write system tools config");
+                }
+
+                Task::none()
+            }
+            Message::SystemToolMovedDown(index) => {
+                if index + 1 < self.config.system_tools.len() {
+                    self.config.system_tools.swap(index, index + 1);
+
+                    self.config
+                        .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                        .expect("Failed to write system tools config");
+                }
+
+                Task::none()
+            }
+            Message::SearchProviderToggled(provider, enabled) => {
+                log::info!("Search provider {:?} changed to: {:?}", provider, enabled);
+                self.config.search_providers.retain(|p| *p != provider);
+                if enabled {
+                    self.config.search_providers.push(provider);
+                }
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write search providers config");
+
+                Task::none()
+            }
+            Message::SearchProviderMovedUp(index) => {
+                if index > 0 && index < self.config.search_providers.len() {
+                    self.config.search_providers.swap(index, index - 1);
+
+                    self.config
+                        .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                        .expect("Failed to write search providers config");
+                }
+
+                Task::none()
+            }
+            Message::SearchProviderMovedDown(index) => {
+                if index + 1 < self.config.search_providers.len() {
+                    self.config.search_providers.swap(index, index + 1);
+
+                    self.config
+                        .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                        .expect("Failed to write search providers config");
+                }
+
+                Task::none()
+            }
+            Message::BuiltinPowerConfirmationToggled(enabled) => {
+                log::info!("Built-in power confirmation changed to: {:?}", enabled);
+                self.config.builtin_power_confirmation = enabled;
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write power confirmation config");
+
+                Task::none()
+            }
+            Message::ScheduledShutdownCountdownToggled(enabled) => {
+                log::info!("Scheduled shutdown countdown changed to: {:?}", enabled);
+                self.config.show_scheduled_shutdown_countdown = enabled;
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write scheduled shutdown countdown config");
+
+                Task::none()
+            }
+            Message::TransliterateSearchToggled(enabled) => {
+                log::info!("Search transliteration changed to: {:?}", enabled);
+                self.config.transliterate_search = enabled;
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write search transliteration config");
+
+                Task::none()
+            }
+            Message::ClearSearchHistory => {
+                log::info!("Clearing the search history");
+                let mut state = AppletState::state();
+                state.search_history.clear();
+
+                match AppletState::state_handler() {
+                    Some(handler) => {
+                        if let Err(e) = state.write_entry(&handler) {
+                            log::error!("Failed to write search history state: {}", e);
+                        }
+                    }
+                    None => log::error!("Failed to open the applet state"),
+                }
+
+                Task::none()
+            }
+            Message::PowerConfirmationToggled(action, enabled) => {
+                log::info!("Confirmation of {:?} changed to: {:?}", action, enabled);
+                self.config.confirm_power_actions.retain(|a| *a != action);
+                if enabled {
+                    self.config.confirm_power_actions.push(action);
+                }
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write power confirmation config");
+
+                Task::none()
+            }
+            Message::PowerActionToggled(action, enabled) => {
+                log::info!("Power button {:?} changed to: {:?}", action, enabled);
+                self.config.enabled_power_actions.retain(|a| *a != action);
+                if enabled {
+                    self.config.enabled_power_actions.push(action);
+                }
+
+                self.config
+                    .write_entry(AppletConfig::config_handler().as_ref().unwrap())
+                    .expect("Failed to write power buttons config");
+
+                Task::none()
+            }
             Message::ToggleContextPage(context_page) => {
                 if self.context_page == context_page {
                     // Close the context drawer if the toggled context page is the same.
@@ -426,6 +782,27 @@ impl cosmic::Application for AppModel {
 }
 
 impl AppModel {
+    /// Index of the configured terminal in the terminal dropdown.
+    ///
+    /// A terminal that isn't installed shows the automatic choice, which the applet falls
+    /// back to as well.
+    fn selected_terminal(&self) -> Option<usize> {
+        Some(self.selected_terminal_position().unwrap_or(0))
+    }
+
+    /// Index of the configured terminal in the terminal dropdown, `None` when it isn't
+    /// installed.
+    fn selected_terminal_position(&self) -> Option<usize> {
+        if self.config.terminal.is_empty() {
+            return Some(0);
+        }
+
+        self.terminals
+            .iter()
+            .position(|t| t.exec == self.config.terminal)
+            .map(|i| i + 1)
+    }
+
     /// Helper to find available system icons in standard locations.
     fn system_icon_names() -> Vec<String> {
         // Prefer runtime discovery using XDG_DATA_DIRS so the app works correctly
@@ -489,6 +866,96 @@ impl AppModel {
         None
     }
 
+    pub fn system_tools_editor(&'_ self) -> Element<'_, Message> {
+        let theme = cosmic::theme::active();
+        let theme = theme.cosmic();
+        let tools_count = self.config.system_tools.len();
+
+        let mut list = cosmic::widget::list_column();
+        for (index, tool) in self.config.system_tools.iter().enumerate() {
+            list = list.add(
+                cosmic::iced_widget::row![
+                    cosmic::iced_widget::column![
+                        text::body(tool.display_name()),
+                        text::caption(tool.target.clone()),
+                    ]
+                    .width(Length::Fill),
+                    button::icon(icon::from_name("go-up-symbolic"))
+                        .on_press_maybe((index > 0).then_some(Message::SystemToolMovedUp(index))),
+                    button::icon(icon::from_name("go-down-symbolic")).on_press_maybe(
+                        (index + 1 < tools_count).then_some(Message::SystemToolMovedDown(index))
+                    ),
+                    button::icon(icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::SystemToolRemoved(index)),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(theme.space_xxs()),
+            );
+        }
+
+        let new_tool = cosmic::iced_widget::column![
+            cosmic::widget::text_input(fl!("system-tool-label"), &self.new_tool_label)
+                .on_input(Message::NewSystemToolLabelChanged),
+            cosmic::widget::text_input(fl!("system-tool-target"), &self.new_tool_target)
+                .on_input(Message::NewSystemToolTargetChanged),
+            cosmic::widget::button::standard(fl!("add")).on_press_maybe(
+                (!self.new_tool_target.trim().is_empty()).then_some(Message::SystemToolAdded)
+            ),
+        ]
+        .spacing(theme.space_xs());
+
+        cosmic::iced_widget::column![list, new_tool]
+            .width(Length::Fill)
+            .spacing(theme.space_m())
+            .into()
+    }
+
+    /// Lists the enabled search providers in their order, followed by the disabled ones.
+    pub fn search_providers_editor(&'_ self) -> Element<'_, Message> {
+        let theme = cosmic::theme::active();
+        let theme = theme.cosmic();
+        let enabled_count = self.config.search_providers.len();
+
+        let disabled = SearchProviderKind::ALL
+            .into_iter()
+            .filter(|provider| !self.config.search_providers.contains(provider));
+
+        let mut list = cosmic::widget::list_column();
+        for (index, provider) in self
+            .config
+            .search_providers
+            .iter()
+            .copied()
+            .chain(disabled)
+            .enumerate()
+        {
+            let is_enabled = index < enabled_count;
+
+            list = list.add(
+                cosmic::iced_widget::row![
+                    text::body(provider.display_name()).width(Length::Fill),
+                    button::icon(icon::from_name("go-up-symbolic")).on_press_maybe(
+                        (is_enabled && index > 0).then_some(Message::SearchProviderMovedUp(index))
+                    ),
+                    button::icon(icon::from_name("go-down-symbolic")).on_press_maybe(
+                        (index + 1 < enabled_count)
+                            .then_some(Message::SearchProviderMovedDown(index))
+                    ),
+                    cosmic::widget::toggler(is_enabled).on_toggle(move |enabled| {
+                        Message::SearchProviderToggled(provider, enabled)
+                    }),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(theme.space_xxs()),
+            );
+        }
+
+        cosmic::iced_widget::column![list]
+            .width(Length::Fill)
+            .spacing(theme.space_m())
+            .into()
+    }
+
     pub fn icon_picker(&'_ self) -> Element<'_, Message> {
         let mut icons = Self::system_icon_names();
         let icons_per_row = 3;
@@ -639,4 +1106,6 @@ pub enum ContextPage {
     #[default]
     About,
     IconPicker, // 1. Add new variant
+    SystemTools,
+    SearchProviders,
 }
@@ -13,6 +13,8 @@ fn main() -> cosmic::iced::Result {
 
     // Enable localizations to be applied.
     i18n::init(&requested_languages);
+    // Strings shared with the applet, such as power action names.
+    cosmic_ext_classic_menu_applet::i18n::init(&requested_languages);
 
     // Settings for configuring the application window and iced runtime.
     let settings = cosmic::app::Settings::default()